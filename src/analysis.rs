@@ -0,0 +1,181 @@
+// `stats`: static metrics describing a program's shape
+use std::collections::BTreeMap;
+
+use brainfuck_jit::{
+    ir::compile,
+    load_state,
+    program::{Program, MAX_NESTING},
+    split_source, Operations,
+};
+
+pub const STATS_USAGE: &str = "stats [filename]";
+
+pub const CHECK_USAGE: &str = "check [filename...]";
+
+// what the analysis found out about a program
+pub struct ProgramStats {
+    counts: BTreeMap<char, usize>,
+    comments: usize,
+    loops_by_depth: BTreeMap<usize, usize>,
+    longest_runs: BTreeMap<char, usize>,
+    balanced_loops: usize,
+    fused_instructions: usize,
+}
+
+pub fn analyze(operations: &[Operations]) -> Result<ProgramStats, String> {
+    let code: Vec<char> = operations.iter().filter_map(Operations::command).collect();
+    let mut stats = ProgramStats {
+        counts: BTreeMap::new(),
+        comments: operations.len() - code.len(),
+        loops_by_depth: BTreeMap::new(),
+        longest_runs: BTreeMap::new(),
+        balanced_loops: 0,
+        fused_instructions: 0,
+    };
+
+    // each open loop remembers where the pointer was, relative to the start, when it opened,
+    // so a loop is balanced if the pointer is back there at its end
+    let mut open: Vec<isize> = Vec::new();
+    let mut pointer: isize = 0;
+    for (i, &c) in code.iter().enumerate() {
+        *stats.counts.entry(c).or_insert(0) += 1;
+        match c {
+            '>' => pointer += 1,
+            '<' => pointer -= 1,
+            '[' if open.len() >= MAX_NESTING => {
+                return Err(format!(
+                    "loops nested more than {} deep at instruction {}",
+                    MAX_NESTING, i
+                ))
+            }
+            '[' => {
+                *stats.loops_by_depth.entry(open.len() + 1).or_insert(0) += 1;
+                open.push(pointer);
+            }
+            ']' => match open.pop() {
+                Some(start) if start == pointer => stats.balanced_loops += 1,
+                Some(_) => {}
+                None => return Err(format!("unmatched `]` at instruction {}", i)),
+            },
+            _ => {}
+        }
+    }
+    if !open.is_empty() {
+        return Err(format!("{} unmatched `[`", open.len()));
+    }
+
+    for run in code.chunk_by(|a, b| a == b) {
+        let longest = stats.longest_runs.entry(run[0]).or_insert(0);
+        *longest = (*longest).max(run.len());
+    }
+    // what plain runs actually dispatch, once the optimizer has fused what it can
+    stats.fused_instructions = compile(operations).len();
+    Ok(stats)
+}
+
+impl ProgramStats {
+    pub fn print(&self) {
+        let total: usize = self.counts.values().sum();
+        let loops: usize = self.loops_by_depth.values().sum();
+        println!("instructions: {}", total);
+        println!("comment characters: {}", self.comments);
+        for c in ['+', '-', '>', '<', '.', ',', '['] {
+            println!(
+                "  `{}`: {}",
+                c,
+                self.counts.get(&c).copied().unwrap_or_default()
+            );
+        }
+        println!("loops: {}", loops);
+        println!(
+            "max loop depth: {}",
+            self.loops_by_depth
+                .keys()
+                .last()
+                .copied()
+                .unwrap_or_default()
+        );
+        for (depth, count) in &self.loops_by_depth {
+            println!("  depth {}: {}", depth, count);
+        }
+        println!("longest runs:");
+        for c in ['+', '-', '>', '<'] {
+            if let Some(run) = self.longest_runs.get(&c) {
+                println!("  `{}`: {}", c, run);
+            }
+        }
+        if loops > 0 {
+            println!(
+                "balanced loops: {} ({:.1}%)",
+                self.balanced_loops,
+                self.balanced_loops as f64 * 100.0 / loops as f64
+            );
+        }
+        if total > 0 {
+            println!(
+                "fused instructions: {} (headroom {:.1}%)",
+                self.fused_instructions,
+                (total - self.fused_instructions) as f64 * 100.0 / total as f64
+            );
+        }
+    }
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    if args.len() != 1 {
+        println!("Usage: {} {}", name, STATS_USAGE);
+        return 1;
+    }
+    let contents = match std::fs::read_to_string(&args[0]) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Unable to read {}: {}", args[0], e);
+            return 1;
+        }
+    };
+    match analyze(&load_state(&contents).operations) {
+        Ok(stats) => {
+            stats.print();
+            0
+        }
+        Err(e) => {
+            println!("{}", e);
+            1
+        }
+    }
+}
+
+// `check`: say whether each file parses, without running anything
+pub fn check_main(name: &str, args: &[String]) -> i32 {
+    if args.is_empty() {
+        println!("Usage: {} {}", name, CHECK_USAGE);
+        return 1;
+    }
+    let mut status = 0;
+    for path in args {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("Unable to read {}: {}", path, e);
+                status = 1;
+                continue;
+            }
+        };
+        let (source, _) = split_source(&contents);
+        match Program::parse(source.as_bytes()) {
+            Ok(program) => {
+                let count = program
+                    .operations
+                    .iter()
+                    .filter_map(Operations::command)
+                    .count();
+                println!("{}: ok, {} instructions", path, count);
+            }
+            Err(e) => {
+                println!("{}: {}", path, e);
+                status = 1;
+            }
+        }
+    }
+    status
+}
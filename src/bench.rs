@@ -0,0 +1,176 @@
+// `bench`: time programs, and compare the timings against a saved baseline
+use std::{collections::BTreeMap, fs, io, time::Instant};
+
+use brainfuck_jit::{load_state, program::Program, split_source};
+
+pub const USAGE: &str =
+    "bench [--runs N] [--baseline file.json] [--save file.json] [--threshold PCT] [filename...]";
+
+const DEFAULT_RUNS: usize = 5;
+const DEFAULT_THRESHOLD: f64 = 10.0;
+
+// the execution strategies that get timed for every program
+const BACKENDS: [&str; 1] = ["interpreter"];
+
+// benchmark results, keyed by `program/backend`, in nanoseconds per run
+type Results = BTreeMap<String, f64>;
+
+struct BenchOptions {
+    files: Vec<String>,
+    runs: usize,
+    baseline: Option<String>,
+    save: Option<String>,
+    threshold: f64,
+}
+
+fn parse_args(name: &str, args: &[String]) -> Option<BenchOptions> {
+    let mut options = BenchOptions {
+        files: Vec::new(),
+        runs: DEFAULT_RUNS,
+        baseline: None,
+        save: None,
+        threshold: DEFAULT_THRESHOLD,
+    };
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--runs" => options.runs = rest.next()?.parse().ok().filter(|&n| n > 0)?,
+            "--baseline" => options.baseline = Some(rest.next()?.clone()),
+            "--save" => options.save = Some(rest.next()?.clone()),
+            "--threshold" => options.threshold = rest.next()?.trim_end_matches('%').parse().ok()?,
+            _ if !arg.starts_with("--") => options.files.push(arg.clone()),
+            _ => return None,
+        }
+    }
+    if options.files.is_empty() {
+        println!("Usage: {} {}", name, USAGE);
+        return None;
+    }
+    Some(options)
+}
+
+// run one program `runs` times, returning the median time per run
+fn time_program(contents: &str, runs: usize) -> f64 {
+    let mut times: Vec<f64> = (0..runs)
+        .map(|_| {
+            let mut state = load_state(contents);
+            state.output = Box::new(io::sink());
+            let start = Instant::now();
+            state.run();
+            start.elapsed().as_nanos() as f64
+        })
+        .collect();
+    times.sort_by(f64::total_cmp);
+    times[times.len() / 2]
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let options = match parse_args(name, args) {
+        Some(options) => options,
+        None => return 1,
+    };
+    let baseline = match &options.baseline {
+        Some(path) => match fs::read_to_string(path).ok().and_then(|s| from_json(&s)) {
+            Some(baseline) => Some(baseline),
+            None => {
+                println!("Unable to read baseline from {}!", path);
+                return 1;
+            }
+        },
+        None => None,
+    };
+
+    let mut results = Results::new();
+    let mut regressions = 0;
+    for file in &options.files {
+        let contents = match fs::read_to_string(file) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("Unable to read {}: {}", file, e);
+                return 1;
+            }
+        };
+        if let Err(e) = Program::parse(split_source(&contents).0.as_bytes()) {
+            println!("{}: {}", file, e);
+            return 1;
+        }
+        for backend in BACKENDS {
+            let key = format!("{}/{}", file, backend);
+            let nanos = time_program(&contents, options.runs);
+            let mut line = format!("{:<40} {:>12.3}ms", key, nanos / 1e6);
+            if let Some(&before) = baseline.as_ref().and_then(|b| b.get(&key)) {
+                let change = (nanos - before) / before * 100.0;
+                line += &format!("  ({:+.1}% vs {:.3}ms)", change, before / 1e6);
+                if change > options.threshold {
+                    line += "  REGRESSED";
+                    regressions += 1;
+                }
+            }
+            println!("{}", line);
+            results.insert(key, nanos);
+        }
+    }
+
+    if let Some(path) = &options.save {
+        if let Err(e) = fs::write(path, to_json(&results)) {
+            println!("Unable to save results to {}: {}", path, e);
+            return 1;
+        }
+    }
+    if regressions > 0 {
+        println!(
+            "{} benchmark(s) regressed by more than {}%",
+            regressions, options.threshold
+        );
+        return 1;
+    }
+    0
+}
+
+// results are stored as a flat json object of `"program/backend": nanoseconds`
+fn to_json(results: &Results) -> String {
+    let entries: Vec<String> = results
+        .iter()
+        .map(|(key, nanos)| {
+            let key = key.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("  \"{}\": {:.0}", key, nanos)
+        })
+        .collect();
+    format!("{{\n{}\n}}\n", entries.join(",\n"))
+}
+
+fn from_json(text: &str) -> Option<Results> {
+    let mut results = Results::new();
+    let mut chars = text
+        .trim()
+        .strip_prefix('{')?
+        .strip_suffix('}')?
+        .chars()
+        .peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+        match chars.next() {
+            None => return Some(results),
+            Some('"') => {}
+            Some(_) => return None,
+        }
+        let mut key = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => key.push(chars.next()?),
+                c => key.push(c),
+            }
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.next()? != ':' {
+            return None;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut number = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ',') {
+            number.push(c);
+        }
+        results.insert(key, number.parse().ok()?);
+    }
+}
@@ -0,0 +1,309 @@
+// `bfasm`: a small structured language that compiles down to brainfuck
+//
+//     # comments run to the end of the line
+//     var n c             declare variables, one cell each
+//     set n 5             n = constant
+//     add c 48            c += constant (`sub` for -=)
+//     add c n             c += n, keeping n (`sub` for -=)
+//     copy n c            c = n, keeping n
+//     move n c            c += n, leaving n at zero
+//     clear n             n = 0
+//     read c              c = next input byte
+//     print c             output c
+//     print "text"        output a string literal
+//     while n { ... }     repeat while n is nonzero
+//     if n { ... }        run once if n is nonzero
+use std::{collections::HashMap, fs};
+
+use crate::encoder::{emit, encode_runs, repeat, wrapping_delta};
+
+pub const USAGE: &str = "bfasm [filename] [-o output.bf]";
+
+// emits code while keeping track of where the tape pointer is
+struct Compiler {
+    vars: HashMap<String, usize>,
+    next_temp: usize,
+    pos: usize,
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl Compiler {
+    // move the pointer to `cell`
+    fn goto(&mut self, code: &mut String, cell: usize) {
+        if cell > self.pos {
+            code.push_str(&">".repeat(cell - self.pos));
+        } else {
+            code.push_str(&"<".repeat(self.pos - cell));
+        }
+        self.pos = cell;
+    }
+
+    // grab a scratch cell; these are zero when handed out and must be zero when released
+    fn temp(&mut self) -> usize {
+        self.next_temp += 1;
+        self.next_temp - 1
+    }
+
+    fn release(&mut self, count: usize) {
+        self.next_temp -= count;
+    }
+
+    fn var(&self, name: &str) -> Result<usize, String> {
+        self.vars
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("unknown variable `{}`", name))
+    }
+
+    // drain `from` into each of `to`, adding or subtracting one per unit
+    fn transfer(&mut self, code: &mut String, from: usize, to: &[(usize, char)]) {
+        self.goto(code, from);
+        code.push_str("[-");
+        for &(cell, op) in to {
+            self.goto(code, cell);
+            code.push(op);
+        }
+        self.goto(code, from);
+        code.push(']');
+    }
+
+    // dst += src (or -=) without losing src, even when they are the same cell
+    fn add_var(&mut self, code: &mut String, dst: usize, src: usize, op: char) {
+        let (t1, t2) = (self.temp(), self.temp());
+        self.transfer(code, src, &[(t1, '+'), (t2, '+')]);
+        self.transfer(code, t2, &[(src, '+')]);
+        self.transfer(code, t1, &[(dst, op)]);
+        self.release(2);
+    }
+
+    fn emit_line(&mut self, code: String) {
+        if !code.is_empty() {
+            self.lines
+                .push(format!("{}{}", "  ".repeat(self.depth), code));
+        }
+    }
+
+    fn statement(&mut self, words: &[&str], rest: &str) -> Result<(), String> {
+        let mut code = String::new();
+        match words {
+            ["var", ..] => {}
+            ["set", name, value] => {
+                let cell = self.var(name)?;
+                let value = constant(value)?;
+                self.goto(&mut code, cell);
+                code += "[-]";
+                code += &repeat(wrapping_delta(0, value));
+            }
+            [op @ ("add" | "sub"), name, value] => {
+                let dst = self.var(name)?;
+                let sign = if *op == "add" { '+' } else { '-' };
+                if let Ok(src) = self.var(value) {
+                    self.add_var(&mut code, dst, src, sign);
+                } else {
+                    let delta = wrapping_delta(0, constant(value)?);
+                    self.goto(&mut code, dst);
+                    code += &repeat(if sign == '+' { delta } else { -delta });
+                }
+            }
+            ["copy", src, dst] => {
+                let (src, dst) = (self.var(src)?, self.var(dst)?);
+                if src != dst {
+                    self.goto(&mut code, dst);
+                    code += "[-]";
+                    self.add_var(&mut code, dst, src, '+');
+                }
+            }
+            ["move", src, dst] => {
+                let (src, dst) = (self.var(src)?, self.var(dst)?);
+                if src != dst {
+                    self.transfer(&mut code, src, &[(dst, '+')]);
+                }
+            }
+            ["clear", name] => {
+                let cell = self.var(name)?;
+                self.goto(&mut code, cell);
+                code += "[-]";
+            }
+            ["read", name] => {
+                let cell = self.var(name)?;
+                self.goto(&mut code, cell);
+                code += ",";
+            }
+            ["print", name] if !name.starts_with('"') => {
+                let cell = self.var(name)?;
+                self.goto(&mut code, cell);
+                code += ".";
+            }
+            ["print", ..] => {
+                let text = string_literal(rest)?;
+                // the encoder prints from its first cell and counts with the next two
+                let base = self.temp();
+                self.temp();
+                self.temp();
+                self.goto(&mut code, base);
+                code += &encode_runs(&text);
+                code += "[-]";
+                self.release(3);
+            }
+            _ => return Err(format!("unknown statement `{}`", rest)),
+        }
+        self.emit_line(code);
+        Ok(())
+    }
+}
+
+fn constant(word: &str) -> Result<u8, String> {
+    if let Some(c) = word.strip_prefix('\'').and_then(|w| w.strip_suffix('\'')) {
+        let mut chars = c.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii() {
+                return Ok(c as u8);
+            }
+        }
+    }
+    word.parse::<u8>()
+        .map_err(|_| format!("`{}` is not a variable or a number from 0 to 255", word))
+}
+
+// the text of `print "..."`, with \n, \t, \\ and \" escapes
+fn string_literal(statement: &str) -> Result<Vec<u8>, String> {
+    let quoted = statement["print".len()..].trim();
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|q| q.strip_suffix('"'))
+        .ok_or_else(|| format!("malformed string in `{}`", statement))?;
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some(c @ ('\\' | '"')) => c,
+                _ => return Err(format!("bad escape in `{}`", statement)),
+            },
+            c => c,
+        };
+        let mut buf = [0; 4];
+        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    Ok(bytes)
+}
+
+// cut a line at the first `#` that isn't inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '#' if !quoted => return &line[..i],
+            '"' if !escaped => quoted = !quoted,
+            _ => {}
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    line
+}
+
+// compile bfasm source to brainfuck, or report the first error with its line number
+pub fn compile(source: &str) -> Result<String, String> {
+    let lines: Vec<(usize, &str)> = source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, strip_comment(line).trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+
+    // variables get the first cells, so scratch space starts after the last declaration
+    let mut vars = HashMap::new();
+    for (number, line) in &lines {
+        if let Some(names) = line.strip_prefix("var ") {
+            for name in names.split_whitespace() {
+                if name.parse::<u8>().is_ok() || vars.contains_key(name) {
+                    return Err(format!("line {}: can't declare `{}`", number, name));
+                }
+                let cell = vars.len();
+                vars.insert(name.to_string(), cell);
+            }
+        }
+    }
+    let mut compiler = Compiler {
+        next_temp: vars.len(),
+        vars,
+        pos: 0,
+        lines: Vec::new(),
+        depth: 0,
+    };
+
+    // each open block remembers the cell its loop tests, and the temp to release (for `if`)
+    let mut blocks: Vec<(usize, usize, bool)> = Vec::new();
+    for (number, line) in &lines {
+        let at_line = |e: String| format!("line {}: {}", number, e);
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let mut code = String::new();
+        match words.as_slice() {
+            [kind @ ("while" | "if"), name, "{"] => {
+                let cell = compiler.var(name).map_err(at_line)?;
+                if *kind == "while" {
+                    compiler.goto(&mut code, cell);
+                    code += "[";
+                    blocks.push((cell, *number, false));
+                } else {
+                    let flag = compiler.temp();
+                    compiler.add_var(&mut code, flag, cell, '+');
+                    compiler.goto(&mut code, flag);
+                    code += "[";
+                    blocks.push((flag, *number, true));
+                }
+                compiler.emit_line(code);
+                compiler.depth += 1;
+            }
+            ["}"] => {
+                let (cell, _, is_if) = blocks
+                    .pop()
+                    .ok_or_else(|| at_line("`}` without a block".to_string()))?;
+                compiler.depth -= 1;
+                compiler.goto(&mut code, cell);
+                if is_if {
+                    code += "[-]";
+                    compiler.release(1);
+                }
+                code += "]";
+                compiler.emit_line(code);
+            }
+            _ => compiler.statement(&words, line).map_err(at_line)?,
+        }
+    }
+    if let Some((_, number, _)) = blocks.pop() {
+        return Err(format!("line {}: block is never closed", number));
+    }
+    let mut program = compiler.lines.join("\n");
+    program.push('\n');
+    Ok(program)
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let (input, output) = match args {
+        [input] => (input, None),
+        [input, flag, output] if flag == "-o" => (input, Some(output)),
+        _ => {
+            println!("Usage: {} {}", name, USAGE);
+            return 1;
+        }
+    };
+    let source = match fs::read_to_string(input) {
+        Ok(source) => source,
+        Err(e) => {
+            println!("Unable to read {}: {}", input, e);
+            return 1;
+        }
+    };
+    match compile(&source) {
+        Ok(program) => emit(&program, output),
+        Err(e) => {
+            println!("{}: {}", input, e);
+            1
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// `match`: where the bracket paired with the one at a position is, for editors that want
+// jump-to-match without a language server. it prints `line:column` of the partner, or
+// `error: ...` and a failing exit, so it's easy to read back from a script. brackets are
+// matched by scanning out from the one asked about, so the rest of the file doesn't have to
+// balance while it's being edited
+use std::fs;
+
+use brainfuck_jit::program::{line_col, offset_of};
+
+pub const USAGE: &str = "match filename --pos line:column";
+
+// the offset of the bracket paired with the one at `offset` in `code`
+fn partner(code: &[u8], offset: usize) -> Result<usize, &'static str> {
+    let forward = match code.get(offset) {
+        Some(b'[') => true,
+        Some(b']') => false,
+        _ => return Err("there's no bracket there"),
+    };
+    let mut depth = 0usize;
+    let mut at = offset;
+    loop {
+        match (code[at], forward) {
+            (b'[', true) | (b']', false) => depth += 1,
+            (b'[', false) | (b']', true) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(at);
+                }
+            }
+            _ => {}
+        }
+        at = match forward {
+            true if at + 1 < code.len() => at + 1,
+            false if at > 0 => at - 1,
+            true => return Err("this `[` is never closed"),
+            false => return Err("this `]` has no `[` before it"),
+        };
+    }
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let usage = || {
+        println!("Usage: {} {}", name, USAGE);
+        1
+    };
+    let (mut path, mut position) = (None, None);
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--pos" => {
+                let parsed = rest.next().and_then(|pos| {
+                    let (line, column) = pos.split_once(':')?;
+                    Some((line.parse::<usize>().ok()?, column.parse::<usize>().ok()?))
+                });
+                match parsed {
+                    Some(pos) => position = Some(pos),
+                    None => return usage(),
+                }
+            }
+            _ if arg.starts_with('-') || path.is_some() => return usage(),
+            _ => path = Some(arg),
+        }
+    }
+    let (Some(path), Some((line, column))) = (path, position) else {
+        return usage();
+    };
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Unable to read {}: {}", path, e);
+            return 1;
+        }
+    };
+    // the program ends where its embedded input starts
+    let end = contents.iter().position(|&b| b == b'!');
+    let code = &contents[..end.unwrap_or(contents.len())];
+    let found = offset_of(&contents, line, column)
+        .ok_or("that position isn't in the file")
+        .and_then(|offset| partner(code, offset));
+    match found {
+        Ok(offset) => {
+            let (line, column) = line_col(&contents, offset);
+            println!("{}:{}", line, column);
+            0
+        }
+        Err(message) => {
+            println!("error: {}", message);
+            1
+        }
+    }
+}
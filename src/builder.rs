@@ -0,0 +1,205 @@
+// assembling a machine with extensions provided by the embedding application
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    rc::Rc,
+    time::Duration,
+};
+
+use crate::events::{EventFn, ExtensionEvent, Verdict};
+use crate::grid::{self, Grid};
+use crate::observer::Observer;
+use crate::progress::{Heartbeat, HeartbeatFn};
+use crate::{Cell, Edge, Interpreter, Operations, OutputMode, Overflow};
+
+// a host function bound to an extension op: it gets the current cell and returns its new value
+pub type HostFunction = Box<dyn FnMut(Cell) -> Cell>;
+
+// characters that already mean something in a source file
+const RESERVED: &str = "+-<>.,[]!";
+
+// builds an `Interpreter` for a program plus whatever extension ops the host registers, e.g.
+//
+//     Builder::new("+++%.").host_function('%', |cell| cell * 2).build()
+pub struct Builder {
+    program: String,
+    input: String,
+    reader: Option<Box<dyn Read>>,
+    writer: Option<Box<dyn Write>>,
+    observer: Option<Box<dyn Observer>>,
+    host_functions: HashMap<char, HostFunction>,
+    heartbeat: Option<(usize, HeartbeatFn)>,
+    extension_events: Option<EventFn>,
+    output_mode: OutputMode,
+    tape_size: Option<usize>,
+    overflow: Overflow,
+    edge: Edge,
+    grid: Option<Grid>,
+    max_steps: Option<usize>,
+    time_limit: Option<Duration>,
+}
+
+impl Builder {
+    pub fn new(program: &str) -> Builder {
+        Builder {
+            program: program.to_string(),
+            input: String::new(),
+            reader: None,
+            writer: None,
+            observer: None,
+            host_functions: HashMap::new(),
+            heartbeat: None,
+            extension_events: None,
+            output_mode: OutputMode::default(),
+            tape_size: None,
+            overflow: Overflow::default(),
+            edge: Edge::default(),
+            grid: None,
+            max_steps: None,
+            time_limit: None,
+        }
+    }
+
+    // the bytes `,` reads
+    pub fn input(mut self, input: &str) -> Builder {
+        self.input = input.to_string();
+        self
+    }
+
+    // tell `observer` about each instruction and byte of i/o as the program runs
+    pub fn observer(mut self, observer: impl Observer + 'static) -> Builder {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    // read `,`'s bytes from `reader` instead, as they're needed
+    pub fn reader(mut self, reader: impl Read + 'static) -> Builder {
+        self.reader = Some(Box::new(reader));
+        self
+    }
+
+    // write `.`'s output to `writer` instead of standard output, e.g. a `stream::Pending` to
+    // read it back afterwards
+    pub fn writer(mut self, writer: impl Write + 'static) -> Builder {
+        self.writer = Some(Box::new(writer));
+        self
+    }
+
+    // make `op` call `function`; panics if `op` is already a brainfuck command
+    pub fn host_function(
+        mut self,
+        op: char,
+        function: impl FnMut(Cell) -> Cell + 'static,
+    ) -> Builder {
+        assert!(!RESERVED.contains(op), "`{}` can't be an extension op", op);
+        self.host_functions.insert(op, Box::new(function));
+        self
+    }
+
+    // call `callback` every `every` steps with how the run is going, and once when it halts
+    pub fn heartbeat(
+        mut self,
+        every: usize,
+        callback: impl FnMut(&Heartbeat) + 'static,
+    ) -> Builder {
+        self.heartbeat = Some((every, Box::new(callback)));
+        self
+    }
+
+    // ask `callback` before each extension side effect, e.g. to log, mock or refuse host calls
+    pub fn extension_events(
+        mut self,
+        callback: impl FnMut(&ExtensionEvent) -> Verdict + 'static,
+    ) -> Builder {
+        self.extension_events = Some(Box::new(callback));
+        self
+    }
+
+    // how `.` turns cells into output; `OutputMode::Bytes` keeps binary output intact
+    pub fn output_mode(mut self, mode: OutputMode) -> Builder {
+        self.output_mode = mode;
+        self
+    }
+
+    // how many cells the tape has, or starts with if it grows, instead of the edge's default
+    pub fn tape_size(mut self, cells: usize) -> Builder {
+        self.tape_size = Some(cells);
+        self
+    }
+
+    // what `+` and `-` do at the ends of a cell's range, instead of wrapping round
+    pub fn overflow(mut self, overflow: Overflow) -> Builder {
+        self.overflow = overflow;
+        self
+    }
+
+    // what `<` and `>` do at the ends of the tape, instead of wrapping round
+    pub fn tape_edge(mut self, edge: Edge) -> Builder {
+        self.edge = edge;
+        self
+    }
+
+    // lay the tape out as `grid`, with `^` and `v` moving up and down its rows
+    pub fn grid(mut self, grid: Grid) -> Builder {
+        self.grid = Some(grid);
+        self
+    }
+
+    // trap once the program has run `steps` instructions, so an endless loop can't hang
+    // the host
+    pub fn max_steps(mut self, steps: usize) -> Builder {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    // trap once the program has been running for `limit` of wall-clock time (a `,` waiting
+    // on input isn't cut short, though)
+    pub fn time_limit(mut self, limit: Duration) -> Builder {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        let mut state = Interpreter::new(&self.program, &self.input);
+        if let Some(reader) = self.reader {
+            state.input = reader;
+        }
+        if let Some(writer) = self.writer {
+            state.output = writer;
+        }
+        if let Some(observer) = self.observer {
+            state.observer = Some(observer);
+        }
+        for (op, function) in self.host_functions {
+            state.bind_host_function(op, function);
+        }
+        if let Some((every, callback)) = self.heartbeat {
+            state.on_heartbeat(every, callback);
+        }
+        state.extension_events = self.extension_events;
+        state.max_steps = self.max_steps;
+        state.time_limit = self.time_limit;
+        state.output_mode = self.output_mode;
+        state.memory.overflow = self.overflow;
+        state.memory.edge = self.edge;
+        state
+            .memory
+            .resize(self.tape_size.unwrap_or(self.edge.default_size()));
+        if let Some(grid) = self.grid {
+            state.use_grid(grid, grid::UP, grid::DOWN);
+        }
+        state
+    }
+}
+
+impl Interpreter {
+    // make `op` call `function` on a machine that's already been set up
+    pub fn bind_host_function(&mut self, op: char, function: HostFunction) {
+        for operation in Rc::make_mut(&mut self.operations).iter_mut() {
+            if matches!(*operation, Operations::Comment(c) if c == op) {
+                *operation = Operations::Host(op);
+            }
+        }
+        self.host_functions.insert(op, function);
+    }
+}
@@ -0,0 +1,196 @@
+// `--crash-dir`: when a run traps, everything needed to make it happen again goes into one
+// file there: the program, the input it had read, the command line, the machine as it stood
+// and its last few steps, so a bug report can just attach it. the run itself isn't slowed
+// down to keep those steps: they're found by running it again one step at a time, from the
+// tape it started on and the input it read, once it has trapped
+use std::{
+    cell::RefCell,
+    env,
+    fmt::Write as _,
+    fs,
+    io::{self, Read},
+    mem,
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use brainfuck_jit::{history::History, trap::Trap, Interpreter, Memory};
+
+// how many of the last steps a bundle shows
+pub const BUNDLE_STEPS: usize = 32;
+
+// cells either side of the pointer a bundle shows
+const CELLS_AROUND: isize = 16;
+
+// how long running a trapped program again to find its last steps may take
+const REPLAY_TIME: Duration = Duration::from_secs(10);
+
+// what a machine started with: its tape, and the bytes its input has given it so far
+pub struct Recording {
+    tape: Memory,
+    read: Rc<RefCell<Vec<u8>>>,
+}
+
+struct Recorder {
+    inner: Box<dyn Read>,
+    read: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Read for Recorder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.borrow_mut().extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+// start keeping what `state` reads, for a bundle if it traps; this wraps whatever it reads
+// from, so it goes on once the input is set up
+pub fn record(state: &mut Interpreter) -> Recording {
+    let read = Rc::new(RefCell::new(Vec::new()));
+    let inner = mem::replace(&mut state.input, Box::new(io::empty()));
+    state.input = Box::new(Recorder {
+        inner,
+        read: Rc::clone(&read),
+    });
+    Recording {
+        tape: state.memory.clone(),
+        read,
+    }
+}
+
+// how running a trapped program again went
+enum Replay {
+    // the history of the steps it took, and how many of them to show
+    Steps(History, usize),
+    TooSlow,
+    // it stopped at a different step: the run depends on something besides its tape and input
+    Different(usize),
+}
+
+// run `state`'s program again from the start, one step at a time, as far as it got before
+// it trapped on `trap`, then the step that trapped, if it was one. host functions go along,
+// so extension ops replay too, though they're called a second time to do it
+fn replay(state: &mut Interpreter, trap: &Trap, recording: &Recording) -> Replay {
+    let input = recording.read.borrow().clone();
+    let (steps, max_depth) = (state.steps, state.max_depth);
+    state.rerun(recording.tape.clone(), input, |replay| {
+        replay.history = Some(History::new(BUNDLE_STEPS));
+        replay.max_depth = max_depth;
+        replay.max_steps = Some(steps);
+        replay.time_limit = Some(REPLAY_TIME);
+        let start = Instant::now();
+        replay.run();
+        if replay.steps != steps {
+            return match start.elapsed() >= REPLAY_TIME {
+                true => Replay::TooSlow,
+                false => Replay::Different(replay.steps),
+            };
+        }
+        // a limit stops a run before a step rather than in one, so then the next step never ran
+        replay.clear_trap();
+        (replay.max_steps, replay.time_limit) = (None, None);
+        let trapped = replay
+            .step()
+            .is_err_and(|again| again.message == trap.message);
+        let history = replay.history.take().expect("the replay keeps a history");
+        Replay::Steps(history, steps + trapped as usize)
+    })
+}
+
+// the bundle for `state`, which stopped with `trap`
+fn render(path: &str, state: &mut Interpreter, trap: &Trap, recording: &Recording) -> String {
+    let mut text = String::new();
+    let args: Vec<String> = env::args().collect();
+    let _ = writeln!(text, "# {}: trapped", path);
+    let _ = writeln!(text, "\n## error\n{}", trap.describe(&state.source));
+    let _ = writeln!(text, "\n## command line\n{}", args.join(" "));
+    let _ = writeln!(
+        text,
+        "\n## program\n{}",
+        String::from_utf8_lossy(&state.source).trim_end()
+    );
+    let consumed = recording.read.borrow();
+    let _ = writeln!(
+        text,
+        "\n## input read ({} bytes)\n{}",
+        consumed.len(),
+        consumed.escape_ascii()
+    );
+
+    let usage = state.usage();
+    let pointer = state.pointer();
+    let _ = writeln!(text, "\n## state");
+    let _ = writeln!(text, "steps: {}", state.steps);
+    let _ = writeln!(text, "pointer: cell {}", pointer);
+    let _ = writeln!(text, "output_bytes: {}", usage.output_bytes);
+    let (start, cells) = state
+        .memory
+        .window(pointer - CELLS_AROUND..pointer + CELLS_AROUND + 1);
+    for (row, cells) in cells.chunks(16).enumerate() {
+        let shown: Vec<String> = cells
+            .iter()
+            .map(|&value| state.memory.number(value).to_string())
+            .collect();
+        let _ = writeln!(text, "{:5}: {}", start + row as isize * 16, shown.join(" "));
+    }
+
+    let _ = writeln!(text, "\n## last steps, oldest first");
+    let (history, shown) = match replay(state, trap, recording) {
+        Replay::Steps(history, shown) => (history, shown),
+        Replay::TooSlow => {
+            let _ = writeln!(text, "(running it again to find them took too long)");
+            return text;
+        }
+        Replay::Different(steps) => {
+            let _ = writeln!(
+                text,
+                "(running it again stopped after {} steps instead, so they can't be shown)",
+                steps
+            );
+            return text;
+        }
+    };
+    let recent: Vec<_> = history.recent().filter(|undo| undo.steps < shown).collect();
+    let skip = recent.len().saturating_sub(BUNDLE_STEPS);
+    for undo in &recent[skip..] {
+        let command = state
+            .operations
+            .get(undo.index)
+            .and_then(|operation| operation.command())
+            .unwrap_or(' ');
+        let _ = writeln!(
+            text,
+            "step {}: `{}` at {}, on cell {} holding {}",
+            undo.steps,
+            command,
+            state.place(undo.index),
+            undo.pointer,
+            state.memory.number(undo.value)
+        );
+    }
+    text
+}
+
+// write the bundle for a trapped run of `path` into `dir` as `<name>.crash`, numbered by batch
+// case like reports are; a problem writing it is reported but doesn't change how the run ends
+pub fn write(
+    dir: &str,
+    path: &str,
+    case: Option<usize>,
+    state: &mut Interpreter,
+    trap: &Trap,
+    recording: &Recording,
+) {
+    let name = Path::new(path)
+        .file_stem()
+        .map_or("program".into(), |stem| stem.to_string_lossy());
+    let file = Path::new(dir).join(crate::case_path(&format!("{}.crash", name), case));
+    let written = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&file, render(path, state, trap, recording)));
+    match written {
+        Ok(()) => eprintln!("{}: wrote {}", path, file.display()),
+        Err(e) => eprintln!("Unable to write {}: {}", file.display(), e),
+    }
+}
@@ -0,0 +1,160 @@
+// `compile`: translate a program to c, for building with any c compiler
+use std::{fmt::Write, fs};
+
+use brainfuck_jit::{program::Program, split_source, Operations, ARRAY_SIZE_LIMIT};
+
+use crate::encoder::emit;
+
+pub const USAGE: &str = "compile [--cell-bits 8|16|32] [--tape-size N] [-o output.c] [filename]";
+
+// `text` as a c string literal, with anything but plain ascii as octal escapes
+fn c_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for &byte in text.as_bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            b' '..=b'~' => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\{:03o}", byte);
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+// the c for `program`; `,` reads `input` if there's any embedded, standard input otherwise,
+// and gives 0 at the end of it like the interpreter does; `.` writes the low byte of the
+// cell, as `--output-mode bytes` would
+pub fn to_c(program: &Program, input: Option<&str>, cell_type: &str, tape_size: usize) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "#include <stdint.h>\n#include <stdio.h>\n");
+    let _ = writeln!(out, "static {} tape[{}];", cell_type, tape_size);
+    match input {
+        Some(input) => {
+            let _ = writeln!(out, "static const char input[] = {};", c_string(input));
+            let _ = writeln!(out, "static size_t input_at = 0;\n");
+            let _ = writeln!(out, "static int next_input(void) {{");
+            let _ = writeln!(
+                out,
+                "    return input_at < sizeof input - 1 ? (unsigned char)input[input_at++] : EOF;"
+            );
+            let _ = writeln!(out, "}}\n");
+        }
+        None => {
+            let _ = writeln!(out, "static int next_input(void) {{ return getchar(); }}\n");
+        }
+    }
+    let _ = writeln!(out, "int main(void) {{");
+    let _ = writeln!(out, "    size_t p = 0;");
+    let _ = writeln!(out, "    int c;");
+
+    // runs of `+-` and `<>` become one statement each
+    let commands: Vec<char> = program
+        .operations
+        .iter()
+        .filter_map(Operations::command)
+        .collect();
+    let mut depth = 1;
+    let mut i = 0;
+    while i < commands.len() {
+        let pad = "    ".repeat(depth);
+        let run = commands[i..]
+            .iter()
+            .take_while(|&&c| c == commands[i])
+            .count();
+        match commands[i] {
+            '+' => {
+                let _ = writeln!(out, "{}tape[p] += {};", pad, run);
+            }
+            '-' => {
+                let _ = writeln!(out, "{}tape[p] -= {};", pad, run);
+            }
+            '>' => {
+                let _ = writeln!(out, "{}p = (p + {}) % {};", pad, run % tape_size, tape_size);
+            }
+            '<' => {
+                let step = tape_size - run % tape_size;
+                let _ = writeln!(out, "{}p = (p + {}) % {};", pad, step, tape_size);
+            }
+            '.' => {
+                let _ = writeln!(out, "{}putchar(tape[p]);", pad);
+            }
+            ',' => {
+                let _ = writeln!(out, "{}c = next_input();", pad);
+                let _ = writeln!(out, "{}tape[p] = c == EOF ? 0 : c;", pad);
+            }
+            '[' => {
+                let _ = writeln!(out, "{}while (tape[p]) {{", pad);
+                depth += 1;
+            }
+            ']' => {
+                depth -= 1;
+                let _ = writeln!(out, "{}}}", "    ".repeat(depth));
+            }
+            _ => {}
+        }
+        i += match commands[i] {
+            '+' | '-' | '<' | '>' => run,
+            _ => 1,
+        };
+    }
+    let _ = writeln!(out, "    return 0;\n}}");
+    out
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let usage = || {
+        println!("Usage: {} {}", name, USAGE);
+        1
+    };
+    let (mut cell_type, mut tape_size) = ("uint8_t", ARRAY_SIZE_LIMIT);
+    let (mut output, mut files) = (None, Vec::new());
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--cell-bits" => {
+                cell_type = match rest.next().map(String::as_str) {
+                    Some("8") => "uint8_t",
+                    Some("16") => "uint16_t",
+                    Some("32") => "uint32_t",
+                    _ => return usage(),
+                }
+            }
+            "--tape-size" => match rest.next().and_then(|n| n.parse().ok()) {
+                Some(0) | None => return usage(),
+                Some(n) => tape_size = n,
+            },
+            "-o" => match rest.next() {
+                Some(path) => output = Some(path),
+                None => return usage(),
+            },
+            _ if arg.starts_with('-') => return usage(),
+            _ => files.push(arg),
+        }
+    }
+    let [filename] = files[..] else {
+        return usage();
+    };
+    let contents = match fs::read_to_string(filename) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Unable to read {}: {}", filename, e);
+            return 1;
+        }
+    };
+    let (source, inputs) = split_source(&contents);
+    match Program::parse(source.as_bytes()) {
+        Ok(program) => emit(
+            &to_c(&program, inputs.first().copied(), cell_type, tape_size),
+            output,
+        ),
+        Err(e) => {
+            println!("{}: {}", filename, e);
+            1
+        }
+    }
+}
@@ -0,0 +1,154 @@
+// `test --corpus`: run a directory of programs laid out the way community test suites are:
+// `name.b` (or `name.bf`) is the program, `name.in` what it reads, if anything, and `name.out`
+// what it has to write. the program is run as it is, `!` and all, since the input has a file
+// of its own. lines at the top starting with `%` set the machine up for it:
+//
+//     % cells 16    cell width in bits
+//     % tape 65536  tape length, which then doesn't grow
+//     % eof 0       what `,` gives at the end of input; 0 is the only one there is
+//     % steps N     how many steps it gets before it counts as a failure
+//
+// a program asking for something the interpreter can't do is skipped rather than failed
+use std::{ffi::OsStr, fs, io, path::Path};
+
+use brainfuck_jit::{program::Program, stream::Pending, Edge, Interpreter, OutputMode};
+
+pub const USAGE: &str = "test --corpus dir";
+
+// steps a program gets without a `% steps` line, so one that never ends doesn't hang the run
+const DEFAULT_STEPS: usize = 100_000_000;
+
+enum Outcome {
+    Pass,
+    Fail(String),
+    Skip(String),
+}
+
+// set `state` up as the header asks; an error is why the case has to be skipped
+fn apply_options(header: &[&str], state: &mut Interpreter) -> Result<(), String> {
+    for line in header {
+        let words: Vec<&str> = line.trim_start_matches('%').split_whitespace().collect();
+        let number = || words.get(1).and_then(|word| word.parse::<u64>().ok());
+        match (words.first().copied(), number()) {
+            (Some("cells"), Some(bits @ 1..=63)) => state.memory.modulus = 1 << bits,
+            (Some("cells"), Some(64)) => state.memory.modulus = 0,
+            (Some("tape"), Some(cells)) if cells > 0 => {
+                state.memory.edge = Edge::Wrap;
+                state.memory.resize(cells as usize);
+            }
+            (Some("eof"), Some(0)) => {}
+            (Some("steps"), Some(limit)) => state.max_steps = Some(limit as usize),
+            _ => return Err(format!("unsupported option `{}`", line.trim())),
+        }
+    }
+    Ok(())
+}
+
+// run the case whose program is at `path`
+fn run_case(path: &Path) -> io::Result<Outcome> {
+    let expected = match fs::read(path.with_extension("out")) {
+        Ok(expected) => expected,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(Outcome::Skip("no .out file".to_string()))
+        }
+        Err(e) => return Err(e),
+    };
+    let input = match fs::read(path.with_extension("in")) {
+        Ok(input) => input,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let source = fs::read(path)?;
+    let program = match Program::parse(&source) {
+        Ok(program) => program,
+        Err(e) => return Ok(Outcome::Fail(e.to_string())),
+    };
+    let text = String::from_utf8_lossy(&source);
+    let header: Vec<&str> = text
+        .lines()
+        .take_while(|line| line.starts_with('%'))
+        .collect();
+
+    let mut state = Interpreter::from_program(&program, String::new());
+    state.input = Box::new(io::Cursor::new(input));
+    state.output_mode = OutputMode::Bytes;
+    let output = Pending::default();
+    state.set_output(output.clone());
+    state.max_steps = Some(DEFAULT_STEPS);
+    if let Err(reason) = apply_options(&header, &mut state) {
+        return Ok(Outcome::Skip(reason));
+    }
+
+    // a plain run, so the fused instructions are used; the limit still stops it on time
+    state.run();
+    let out_of_steps = state.max_steps.filter(|&limit| state.steps >= limit);
+    if let (Some(limit), Some(_)) = (out_of_steps, &state.trap) {
+        return Ok(Outcome::Fail(format!(
+            "still running after {} steps",
+            limit
+        )));
+    }
+    if let Some(trap) = &state.trap {
+        return Ok(Outcome::Fail(
+            trap.describe(&state.source).replace('\n', " "),
+        ));
+    }
+    let actual = output.take();
+    Ok(match actual == expected {
+        true => Outcome::Pass,
+        false => Outcome::Fail(format!(
+            "expected \"{}\", got \"{}\"",
+            expected.escape_ascii(),
+            actual.escape_ascii()
+        )),
+    })
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let usage = || {
+        println!("Usage: {} {}", name, USAGE);
+        1
+    };
+    let dir = match args {
+        [flag, dir] if flag == "--corpus" => dir,
+        _ => return usage(),
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("Unable to read {}: {}", dir, e);
+            return 1;
+        }
+    };
+    let mut programs: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| matches!(path.extension().and_then(OsStr::to_str), Some("b" | "bf")))
+        .collect();
+    programs.sort();
+
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for path in &programs {
+        let case = path.file_name().unwrap_or_default().to_string_lossy();
+        match run_case(path) {
+            Ok(Outcome::Pass) => {
+                println!("ok   {}", case);
+                passed += 1;
+            }
+            Ok(Outcome::Fail(why)) => {
+                println!("FAIL {}: {}", case, why);
+                failed += 1;
+            }
+            Ok(Outcome::Skip(why)) => {
+                println!("skip {}: {}", case, why);
+                skipped += 1;
+            }
+            Err(e) => {
+                println!("FAIL {}: unable to read it: {}", case, e);
+                failed += 1;
+            }
+        }
+    }
+    println!("{} passed, {} failed, {} skipped", passed, failed, skipped);
+    (failed > 0) as i32
+}
@@ -0,0 +1,319 @@
+// `debug`: stepping through a program from a prompt, with breakpoints and a look at the tape
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{self, BufRead, Write},
+    ops::Range,
+};
+
+use brainfuck_jit::{step::Effect, step::StepOutcome, Interpreter, Operations};
+
+use crate::{
+    options::{parse_range, CellFormat},
+    print_cells,
+};
+
+const HELP: &str = "commands: s [N] to step, r [N] to step back, c to continue, b / d to set / \
+delete a breakpoint (at instruction N, line:col or a label, optionally `count N` to stop the Nth \
+time it's reached, or @Nstep at step N), p [a..b] to show cells, set cell N = V / set ptr = N \
+to change the tape, e a..b to edit cells one by one, q to quit; `c` also stops before a `#`";
+
+// a change to the machine asked for with `set`
+enum Change {
+    Cell(isize, i128), // as a number, which can be negative
+    Pointer(isize),
+}
+
+// the change in the words after `set`: `cell N = V` or `ptr = N`, the `=` optional
+fn parse_change(words: &[&str]) -> Option<Change> {
+    let words: Vec<&str> = words.iter().copied().filter(|&word| word != "=").collect();
+    match words[..] {
+        ["cell", cell, value] => Some(Change::Cell(cell.parse().ok()?, value.parse().ok()?)),
+        ["ptr" | "pointer", cell] => Some(Change::Pointer(cell.parse().ok()?)),
+        _ => None,
+    }
+}
+
+// go through the cells in `range` asking for each one's new value: a number replaces it,
+// nothing keeps it and `q` stops
+fn edit_cells(
+    state: &mut Interpreter,
+    range: Range<isize>,
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+) {
+    for cell in range {
+        let Some(&value) = state.memory.window(cell..cell + 1).1.first() else {
+            eprintln!("cell {} isn't on the tape", cell);
+            return;
+        };
+        eprint!("cell {} [{}]: ", cell, state.memory.number(value));
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return,
+        };
+        match line.trim() {
+            "" => {}
+            "q" => return,
+            text => match text.parse() {
+                Ok(number) => {
+                    let value = state.memory.cell_for(number);
+                    state.memory.set_cell(cell, value);
+                }
+                Err(_) => {
+                    eprintln!("not a number; stopping");
+                    return;
+                }
+            },
+        }
+    }
+}
+
+// where `c` stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Breakpoint {
+    At(usize),                          // before an instruction
+    Count { index: usize, nth: usize }, // before an instruction runs for the nth time
+    Step(usize),                        // once the program has taken this many steps
+}
+
+// a breakpoint from the words after `b` or `d`
+fn parse_breakpoint(state: &Interpreter, words: &[&str]) -> Option<Breakpoint> {
+    // an instruction number, line:col, or a label's first instruction
+    let location = |text: &str| {
+        let place = match text.split_once(':') {
+            Some((line, column)) => line
+                .parse()
+                .ok()
+                .zip(column.parse().ok())
+                .and_then(|(line, column)| state.index_at(line, column)),
+            None => text.parse().ok(),
+        };
+        place.or_else(|| state.label_index(text))
+    };
+    match words {
+        [step] if step.starts_with('@') => {
+            let steps = step[1..].strip_suffix("step").unwrap_or(&step[1..]);
+            Some(Breakpoint::Step(steps.parse().ok()?))
+        }
+        [at] => Some(Breakpoint::At(location(at)?)),
+        [at, "count", nth] => match nth.parse().ok()? {
+            0 => None,
+            nth => Some(Breakpoint::Count {
+                index: location(at)?,
+                nth,
+            }),
+        },
+        _ => None,
+    }
+}
+
+// why `c` should stop before the next instruction, if it should
+fn stop_reason(
+    state: &Interpreter,
+    breakpoints: &BTreeSet<Breakpoint>,
+    runs: &HashMap<usize, usize>,
+    before: usize, // the step count before the step just taken
+) -> Option<String> {
+    let next = state.next_index();
+    let ran = runs.get(&next).copied().unwrap_or(0);
+    breakpoints.iter().find_map(|breakpoint| match *breakpoint {
+        Breakpoint::At(index) if index == next => Some(String::new()),
+        Breakpoint::Count { index, nth } if index == next && ran + 1 == nth => {
+            Some(format!(", about to run it for time number {}", nth))
+        }
+        // only the step that got there, not the comments after it, which aren't counted
+        Breakpoint::Step(steps) if before != steps && state.steps == steps => {
+            Some(format!(", at step {}", steps))
+        }
+        _ => None,
+    })
+}
+
+// steps kept in memory to step back through; past this they go to `--history-file`, if given,
+// which holds up to `--history-cap` bytes of them
+pub const HISTORY_STEPS: usize = 100_000;
+pub const HISTORY_CAP: u64 = 1 << 30;
+
+// a line about what one step did
+fn describe(state: &Interpreter, outcome: &StepOutcome) -> String {
+    let command = match outcome.operation {
+        Some(Operations::Comment(c)) => c,
+        Some(operation) => operation.command().unwrap_or(' '),
+        None => return "the program has halted".to_string(),
+    };
+    let effect = match outcome.effect {
+        Effect::Nothing => String::new(),
+        Effect::Pointer(cell) => format!(": pointer at cell {}", cell),
+        Effect::Cell { cell, value } => {
+            format!(": cell {} = {}", cell, state.memory.number(value))
+        }
+        Effect::Input { cell, value } => format!(": read {} into cell {}", value, cell),
+        Effect::Output(value) => format!(": wrote {}", value),
+        Effect::Jump(index) => format!(": jumped to instruction {}", index),
+    };
+    format!(
+        "{} `{}` at {}{}",
+        outcome.index,
+        command,
+        state.place(outcome.index),
+        effect
+    )
+}
+
+// run one step, saying what happened when `verbose` and counting it in `runs`; false once
+// there's nothing left to run
+fn step(state: &mut Interpreter, verbose: bool, runs: &mut HashMap<usize, usize>) -> bool {
+    *runs.entry(state.next_index()).or_insert(0) += 1;
+    match state.step() {
+        Ok(outcome) => {
+            if verbose || outcome.operation.is_none() {
+                eprintln!("{}", describe(state, &outcome));
+            }
+            outcome.operation.is_some()
+        }
+        Err(trap) => {
+            eprintln!("{}", trap.describe(&state.source));
+            false
+        }
+    }
+}
+
+// undo up to `count` steps and say where that leaves the program
+fn step_back(state: &mut Interpreter, count: usize, runs: &mut HashMap<usize, usize>) {
+    for done in 0..count {
+        match state.step_back() {
+            Ok(true) => {
+                if let Some(ran) = runs.get_mut(&state.next_index()) {
+                    *ran = ran.saturating_sub(1);
+                }
+            }
+            Ok(false) => {
+                let lost = state.history.as_ref().is_some_and(|history| history.lost());
+                eprintln!(
+                    "{} (went back {} steps)",
+                    match lost {
+                        true => "no more history: older steps were dropped to stay within the cap",
+                        false => "back at the start",
+                    },
+                    done
+                );
+                break;
+            }
+            Err(e) => {
+                eprintln!("Unable to read the history: {}", e);
+                return;
+            }
+        }
+    }
+    let next = state.next_index();
+    eprintln!(
+        "before instruction {} at {}, step {}",
+        next,
+        state.place(next),
+        state.steps
+    );
+}
+
+pub fn main(state: &mut Interpreter, format: CellFormat) -> i32 {
+    let mut breakpoints = BTreeSet::new();
+    let mut runs = HashMap::new(); // times each instruction has been run
+    let mut lines = io::stdin().lock().lines();
+    eprintln!("{}", HELP);
+    loop {
+        let _ = state.output.flush();
+        eprint!("(bf) ");
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return 0,
+        };
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (None, _) | (Some("s" | "step"), None) => {
+                step(state, true, &mut runs);
+            }
+            (Some("s" | "step"), Some(count)) => match count.parse::<usize>() {
+                Ok(count) => {
+                    for _ in 0..count {
+                        if !step(state, true, &mut runs) {
+                            break;
+                        }
+                    }
+                }
+                Err(_) => eprintln!("{}", HELP),
+            },
+            (Some("r" | "reverse"), count) => match count.map_or(Ok(1), str::parse::<usize>) {
+                Ok(count) => step_back(state, count, &mut runs),
+                Err(_) => eprintln!("{}", HELP),
+            },
+            (Some("c" | "continue"), _) => {
+                // always take the first step, so `c` can leave a breakpoint
+                loop {
+                    let before = state.steps;
+                    if !step(state, false, &mut runs) {
+                        break;
+                    }
+                    let next = state.next_index();
+                    let marked =
+                        matches!(state.operations.get(next), Some(Operations::Comment('#')))
+                            .then(String::new);
+                    if let Some(reason) =
+                        marked.or_else(|| stop_reason(state, &breakpoints, &runs, before))
+                    {
+                        eprintln!(
+                            "stopped before instruction {} at {}{}",
+                            next,
+                            state.place(next),
+                            reason
+                        );
+                        break;
+                    }
+                }
+            }
+            (Some(command @ ("b" | "break" | "d" | "delete")), Some(first)) => {
+                let rest: Vec<&str> = [first].into_iter().chain(words).collect();
+                match parse_breakpoint(state, &rest) {
+                    Some(breakpoint) if command.starts_with('b') => {
+                        breakpoints.insert(breakpoint);
+                    }
+                    Some(breakpoint) => {
+                        breakpoints.remove(&breakpoint);
+                    }
+                    None => eprintln!("{}", HELP),
+                }
+            }
+            (Some("p" | "print"), range) => {
+                let pointer = state.pointer();
+                let range = match range {
+                    Some(range) => parse_range(range),
+                    None => Some(pointer - 8..pointer + 8),
+                };
+                match range {
+                    Some(range) => print_cells(&state.memory, range, format),
+                    None => eprintln!("{}", HELP),
+                }
+            }
+            (Some("set"), Some(first)) => {
+                let rest: Vec<&str> = [first].into_iter().chain(words).collect();
+                let done = match parse_change(&rest) {
+                    Some(Change::Cell(cell, number)) => {
+                        let value = state.memory.cell_for(number);
+                        state.memory.set_cell(cell, value)
+                    }
+                    Some(Change::Pointer(cell)) => state.memory.set_pointer(cell),
+                    None => {
+                        eprintln!("{}", HELP);
+                        continue;
+                    }
+                };
+                if !done {
+                    eprintln!("that cell isn't on the tape");
+                }
+            }
+            (Some("e" | "edit"), Some(range)) => match parse_range(range) {
+                Some(range) => edit_cells(state, range, &mut lines),
+                None => eprintln!("{}", HELP),
+            },
+            (Some("q" | "quit"), _) => return 0,
+            _ => eprintln!("{}", HELP),
+        }
+    }
+}
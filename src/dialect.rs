@@ -0,0 +1,201 @@
+// other spellings of the eight commands, and `convert` for translating between them
+use std::fs;
+
+use brainfuck_jit::split_source;
+
+pub const USAGE: &str = "convert --from dialect --to dialect [filename]";
+
+// the commands in the order every dialect lists its tokens
+const COMMANDS: [char; 8] = ['+', '-', '<', '>', '.', ',', '[', ']'];
+
+// a trivial substitution of brainfuck: one token per command, anything else is a comment
+#[derive(Debug, Clone)]
+pub struct Dialect {
+    pub name: String,
+    pub tokens: [String; 8], // in the order of `COMMANDS`
+    pub separator: String,   // written between tokens
+}
+
+// name, tokens and separator of each built-in dialect
+const BUILT_IN: &[(&str, [&str; 8], &str)] = &[
+    ("bf", ["+", "-", "<", ">", ".", ",", "[", "]"], ""),
+    (
+        "ook",
+        [
+            "Ook. Ook.",
+            "Ook! Ook!",
+            "Ook? Ook.",
+            "Ook. Ook?",
+            "Ook! Ook.",
+            "Ook. Ook!",
+            "Ook! Ook?",
+            "Ook? Ook!",
+        ],
+        " ",
+    ),
+    (
+        "blub",
+        [
+            "Blub. Blub.",
+            "Blub! Blub!",
+            "Blub? Blub.",
+            "Blub. Blub?",
+            "Blub! Blub.",
+            "Blub. Blub!",
+            "Blub! Blub?",
+            "Blub? Blub!",
+        ],
+        " ",
+    ),
+    ("alphuck", ["e", "i", "c", "a", "j", "o", "p", "s"], ""),
+    (
+        "spoon",
+        [
+            "1", "000", "011", "010", "001010", "0010110", "00100", "0011",
+        ],
+        "",
+    ),
+];
+
+impl Dialect {
+    // a built-in dialect by name, or `tbs:` and eight comma-separated tokens for any other
+    // substitution, e.g. `tbs:inc,dec,left,right,out,in,do,done`
+    pub fn find(name: &str) -> Result<Dialect, String> {
+        if let Some(tokens) = name.strip_prefix("tbs:") {
+            let tokens: Vec<String> = tokens.split(',').map(str::to_string).collect();
+            let tokens: [String; 8] = tokens
+                .try_into()
+                .map_err(|_| "a `tbs:` dialect needs eight comma-separated tokens".to_string())?;
+            if tokens.iter().any(String::is_empty) {
+                return Err("a `tbs:` dialect can't have empty tokens".to_string());
+            }
+            let separator = if tokens.iter().all(|token| token.chars().count() == 1) {
+                ""
+            } else {
+                " "
+            };
+            return Ok(Dialect {
+                name: name.to_string(),
+                tokens,
+                separator: separator.to_string(),
+            });
+        }
+        let (name, tokens, separator) = BUILT_IN
+            .iter()
+            .find(|(known, _, _)| *known == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = BUILT_IN.iter().map(|(known, _, _)| *known).collect();
+                format!(
+                    "unknown dialect `{}` (try {} or tbs:...)",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+        Ok(Dialect {
+            name: name.to_string(),
+            tokens: tokens.map(str::to_string),
+            separator: separator.to_string(),
+        })
+    }
+
+    // the length of `token` at the start of `text`, if it's there; a space in the token
+    // stands for any run of whitespace
+    fn matches(token: &str, text: &str) -> Option<usize> {
+        let mut parts = token.split(' ');
+        let mut rest = text.strip_prefix(parts.next()?)?;
+        for part in parts {
+            let trimmed = rest.trim_start();
+            if trimmed.len() == rest.len() {
+                return None;
+            }
+            rest = trimmed.strip_prefix(part)?;
+        }
+        Some(text.len() - rest.len())
+    }
+
+    // the brainfuck commands spelt out in `source`; longer tokens are tried first, so `do`
+    // isn't read as `d` and `o`
+    pub fn decode(&self, source: &str) -> String {
+        let mut tokens: Vec<(&String, char)> = self.tokens.iter().zip(COMMANDS).collect();
+        tokens.sort_by_key(|(token, _)| std::cmp::Reverse(token.len()));
+        let mut commands = String::new();
+        let mut rest = source;
+        while let Some(c) = rest.chars().next() {
+            let found = tokens
+                .iter()
+                .find_map(|&(token, command)| Some((Dialect::matches(token, rest)?, command)));
+            match found {
+                Some((len, command)) => {
+                    commands.push(command);
+                    rest = &rest[len..];
+                }
+                None => rest = &rest[c.len_utf8()..],
+            }
+        }
+        commands
+    }
+
+    // `commands` in this dialect; anything that isn't a command is left out
+    pub fn encode(&self, commands: &str) -> String {
+        let tokens: Vec<&str> = commands
+            .chars()
+            .filter_map(|c| COMMANDS.iter().position(|&command| command == c))
+            .map(|i| self.tokens[i].as_str())
+            .collect();
+        tokens.join(&self.separator)
+    }
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let usage = || {
+        println!("Usage: {} {}", name, USAGE);
+        1
+    };
+    let (mut from, mut to, mut files) = (None, None, Vec::new());
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--from" => from = rest.next(),
+            "--to" => to = rest.next(),
+            _ if arg.starts_with("--") => return usage(),
+            _ => files.push(arg),
+        }
+    }
+    let (Some(from), Some(to), [filename]) = (from, to, &files[..]) else {
+        return usage();
+    };
+    let (from, to) = match (Dialect::find(from), Dialect::find(to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => {
+            println!("{}", e);
+            return 1;
+        }
+    };
+    let contents = match fs::read_to_string(filename) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Unable to read {}: {}", filename, e);
+            return 1;
+        }
+    };
+
+    // embedded input after a `!` only means something in plain brainfuck
+    let (program, inputs) = match from.name.as_str() {
+        "bf" => split_source(&contents),
+        _ => (contents.as_str(), Vec::new()),
+    };
+    let mut converted = to.encode(&from.decode(program));
+    if to.name == "bf" {
+        for input in &inputs {
+            converted.push('!');
+            converted.push_str(input);
+        }
+    } else if !inputs.is_empty() {
+        eprintln!(
+            "{}: embedded input left out, {} has no way to write it",
+            filename, to.name
+        );
+    }
+    println!("{}", converted);
+    0
+}
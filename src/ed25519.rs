@@ -0,0 +1,336 @@
+// checking rfc 8032 ed25519 signatures, for `hash --pubkey`: only the holder of the private key
+// can make one, and anyone with the public key can check it. there's no signing here; any
+// ed25519 tool can sign what `hash --commands` prints. nothing checked is secret, so none of
+// this tries to take the same time whatever the input
+use crate::hash::sha512;
+
+// a number mod p = 2^255 - 19, as sixteen 16 bit limbs that are let run over between carries
+type Field = [i64; 16];
+
+// a curve point in extended coordinates: x/z, y/z, and xy/z
+type Point = [Field; 4];
+
+// the order of the base point, little endian
+const ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+];
+
+// the base point's y is 4/5, and its x is the even one
+const BASE: [u8; 32] = [
+    0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+];
+
+fn small(n: i64) -> Field {
+    let mut field = [0; 16];
+    field[0] = n;
+    field
+}
+
+// push each limb's overflow into the next, the top one's round to the bottom: 2^256 is 38 mod p
+fn carry(field: &mut Field) {
+    for i in 0..16 {
+        let over = field[i] >> 16;
+        field[i] -= over << 16;
+        match i {
+            15 => field[0] += 38 * over,
+            _ => field[i + 1] += over,
+        }
+    }
+}
+
+fn add(a: &Field, b: &Field) -> Field {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+
+fn sub(a: &Field, b: &Field) -> Field {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn mul(a: &Field, b: &Field) -> Field {
+    let mut wide = [0i64; 31];
+    for i in 0..16 {
+        for j in 0..16 {
+            wide[i + j] += a[i] * b[j];
+        }
+    }
+    for i in 0..15 {
+        wide[i] += 38 * wide[i + 16];
+    }
+    let mut product: Field = wide[..16].try_into().unwrap();
+    carry(&mut product);
+    carry(&mut product);
+    product
+}
+
+// `base` to the power whose bits are all ones from `top` down, except at `zeros`
+fn power(base: &Field, top: u32, zeros: &[u32]) -> Field {
+    let mut result = *base;
+    for bit in (0..top).rev() {
+        result = mul(&result, &result);
+        if !zeros.contains(&bit) {
+            result = mul(&result, base);
+        }
+    }
+    result
+}
+
+// to the power of p - 2 = 2^255 - 21
+fn invert(field: &Field) -> Field {
+    power(field, 254, &[2, 4])
+}
+
+// fully reduced, little endian
+fn to_bytes(field: &Field) -> [u8; 32] {
+    let mut reduced = *field;
+    for _ in 0..3 {
+        carry(&mut reduced);
+    }
+    // now under 2^256, so taking p off twice where it fits leaves it under p
+    for _ in 0..2 {
+        let mut less = [0i64; 16];
+        let mut borrow = 0;
+        for i in 0..16 {
+            let limb = match i {
+                0 => 0xffed,
+                15 => 0x7fff,
+                _ => 0xffff,
+            };
+            less[i] = reduced[i] - limb - borrow;
+            borrow = (less[i] >> 16) & 1;
+            less[i] &= 0xffff;
+        }
+        if borrow == 0 {
+            reduced = less;
+        }
+    }
+    let mut bytes = [0; 32];
+    for (pair, limb) in bytes.chunks_mut(2).zip(reduced) {
+        pair[0] = limb as u8;
+        pair[1] = (limb >> 8) as u8;
+    }
+    bytes
+}
+
+// the low 255 bits of `bytes`
+fn from_bytes(bytes: &[u8; 32]) -> Field {
+    let mut field: Field =
+        std::array::from_fn(|i| bytes[2 * i] as i64 | (bytes[2 * i + 1] as i64) << 8);
+    field[15] &= 0x7fff;
+    field
+}
+
+fn equal(a: &Field, b: &Field) -> bool {
+    to_bytes(a) == to_bytes(b)
+}
+
+// the curve's constants, worked out once per check
+struct Curve {
+    d: Field,
+    twice_d: Field,
+    root_minus_one: Field,
+}
+
+impl Curve {
+    fn new() -> Curve {
+        let d = mul(&small(-121665), &invert(&small(121666)));
+        Curve {
+            twice_d: add(&d, &d),
+            d,
+            // 2^((p - 1) / 4)
+            root_minus_one: power(&small(2), 252, &[2]),
+        }
+    }
+
+    // the unified addition from hisil et al., which doubles as well
+    fn add(&self, p: &Point, q: &Point) -> Point {
+        let a = mul(&sub(&p[1], &p[0]), &sub(&q[1], &q[0]));
+        let b = mul(&add(&p[1], &p[0]), &add(&q[1], &q[0]));
+        let c = mul(&mul(&p[3], &q[3]), &self.twice_d);
+        let d = mul(&p[2], &q[2]);
+        let d = add(&d, &d);
+        let (e, f, g, h) = (sub(&b, &a), sub(&d, &c), add(&d, &c), add(&b, &a));
+        [mul(&e, &f), mul(&h, &g), mul(&g, &f), mul(&e, &h)]
+    }
+
+    // `scalar` (little endian) times `point`
+    fn times(&self, point: &Point, scalar: &[u8; 32]) -> Point {
+        let mut result = [small(0), small(1), small(1), small(0)];
+        for bit in (0..256).rev() {
+            result = self.add(&result, &result);
+            if scalar[bit / 8] >> (bit % 8) & 1 == 1 {
+                result = self.add(&result, point);
+            }
+        }
+        result
+    }
+
+    fn encode(&self, point: &Point) -> [u8; 32] {
+        let z = invert(&point[2]);
+        let mut bytes = to_bytes(&mul(&point[1], &z));
+        bytes[31] |= (to_bytes(&mul(&point[0], &z))[0] & 1) << 7;
+        bytes
+    }
+
+    // rfc 8032 section 5.1.3: y and x's low bit, or none if that isn't a point
+    fn decode(&self, bytes: &[u8; 32]) -> Option<Point> {
+        let y = from_bytes(bytes);
+        let mut y_bytes = *bytes;
+        y_bytes[31] &= 0x7f;
+        if to_bytes(&y) != y_bytes {
+            return None;
+        }
+        let one = small(1);
+        let y2 = mul(&y, &y);
+        let (u, v) = (sub(&y2, &one), add(&mul(&self.d, &y2), &one));
+        // x = u v^3 (u v^7)^((p - 5) / 8), the square root of u/v if there is one, or of -u/v
+        let v3 = mul(&mul(&v, &v), &v);
+        let v7 = mul(&mul(&v3, &v3), &v);
+        let mut x = mul(&mul(&u, &v3), &power(&mul(&u, &v7), 251, &[1]));
+        let vx2 = mul(&v, &mul(&x, &x));
+        if !equal(&vx2, &u) {
+            if !equal(&vx2, &sub(&small(0), &u)) {
+                return None;
+            }
+            x = mul(&x, &self.root_minus_one);
+        }
+        let sign = bytes[31] >> 7;
+        let x_bytes = to_bytes(&x);
+        if x_bytes == [0; 32] && sign == 1 {
+            return None;
+        }
+        if x_bytes[0] & 1 != sign {
+            x = sub(&small(0), &x);
+        }
+        Some([x, y, one, mul(&x, &y)])
+    }
+}
+
+// whether `scalar`, little endian, is less than the order
+fn below_order(scalar: &[u8; 32]) -> bool {
+    for (byte, limit) in scalar.iter().zip(ORDER).rev() {
+        if *byte != limit {
+            return *byte < limit;
+        }
+    }
+    false
+}
+
+// a 512 bit hash mod the order, by long division a bit at a time
+fn reduce(wide: &[u8; 64]) -> [u8; 32] {
+    let mut remainder = [0u8; 32];
+    for bit in (0..512).rev() {
+        let mut carried = wide[bit / 8] >> (bit % 8) & 1;
+        for byte in remainder.iter_mut() {
+            let shifted = *byte >> 7;
+            *byte = *byte << 1 | carried;
+            carried = shifted;
+        }
+        if !below_order(&remainder) {
+            let mut borrow = 0;
+            for (byte, limit) in remainder.iter_mut().zip(ORDER) {
+                let (less, under) = byte.overflowing_sub(limit);
+                let (less, under_again) = less.overflowing_sub(borrow);
+                *byte = less;
+                borrow = (under || under_again) as u8;
+            }
+        }
+    }
+    remainder
+}
+
+// whether `signature` is `public_key`'s on `message`: [s]B = R + [k]A, k = sha512(R, A, message)
+pub fn verify(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    let curve = Curve::new();
+    let (Some(key), Some(base)) = (curve.decode(public_key), curve.decode(&BASE)) else {
+        return false;
+    };
+    let (r, s): ([u8; 32], [u8; 32]) = (
+        signature[..32].try_into().unwrap(),
+        signature[32..].try_into().unwrap(),
+    );
+    if !below_order(&s) {
+        return false;
+    }
+    let mut hashed = r.to_vec();
+    hashed.extend_from_slice(public_key);
+    hashed.extend_from_slice(message);
+    let k = reduce(&sha512(&hashed));
+    let negated = [
+        sub(&small(0), &key[0]),
+        key[1],
+        key[2],
+        sub(&small(0), &key[3]),
+    ];
+    let check = curve.add(&curve.times(&base, &s), &curve.times(&negated, &k));
+    curve.encode(&check) == r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::unhex;
+
+    fn bytes<const N: usize>(hex: &str) -> [u8; N] {
+        unhex(hex).unwrap().try_into().unwrap()
+    }
+
+    // rfc 8032 section 7.1, tests 1 to 3
+    const VECTORS: [(&str, &str, &str); 3] = [
+        (
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        ),
+        (
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        ),
+        (
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            "af82",
+            "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+        ),
+    ];
+
+    #[test]
+    fn rfc_8032() {
+        for (key, text, signature) in VECTORS {
+            assert!(verify(
+                &bytes(key),
+                &unhex(text).unwrap(),
+                &bytes(signature)
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_changes() {
+        for (key, text, signature) in VECTORS {
+            let (key, signature) = (bytes::<32>(key), bytes::<64>(signature));
+            let mut longer = unhex(text).unwrap();
+            longer.push(0);
+            assert!(!verify(&key, &longer, &signature));
+            for i in [0, 31, 32, 63] {
+                let mut changed = signature;
+                changed[i] ^= 1;
+                assert!(!verify(&key, &unhex(text).unwrap(), &changed));
+            }
+            // the same signature with the order added to s
+            let mut bigger = signature;
+            let mut carried = 0;
+            for (byte, limit) in bigger[32..].iter_mut().zip(ORDER) {
+                let sum = *byte as u16 + limit as u16 + carried;
+                *byte = sum as u8;
+                carried = sum >> 8;
+            }
+            assert!(!verify(&key, &unhex(text).unwrap(), &bigger));
+        }
+        let (key, _, signature) = VECTORS[0];
+        let (other, _, _) = VECTORS[1];
+        assert!(!verify(&bytes(other), b"", &bytes(signature)));
+        assert!(verify(&bytes(key), b"", &bytes(signature)));
+    }
+}
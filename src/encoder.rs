@@ -0,0 +1,268 @@
+// generators for programs that print a fixed sequence of bytes
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+pub const TEXT_USAGE: &str = "encode [text] [-o output.bf] [--search] [--time-budget MS]";
+
+pub const FILE_USAGE: &str = "encode-file [filename] [-o output.bf]";
+
+// runs of identical bytes longer than this are printed with a counting loop
+const RUN_LOOP_THRESHOLD: usize = 6;
+const LINE_WIDTH: usize = 80;
+const DEFAULT_TIME_BUDGET_MS: u64 = 500;
+// the search never spreads the text over more working cells than this
+const MAX_SEARCH_CELLS: usize = 8;
+
+// `n` copies of `+`, or of `-` when `n` is negative
+pub fn repeat(n: i32) -> String {
+    let c = if n < 0 { '-' } else { '+' };
+    c.to_string().repeat(n.unsigned_abs() as usize)
+}
+
+// code that adds `delta` to the current cell, using the cell to its right (which must be zero)
+// as a loop counter whenever a multiplication is shorter than plain `+`/`-`
+fn adjust(delta: i32) -> String {
+    let mut best = repeat(delta);
+    for factor in 2..=16 {
+        let times = delta / factor;
+        let rest = delta - factor * times;
+        if times == 0 {
+            continue;
+        }
+        let candidate = format!(">{}[<{}>-]<{}", repeat(factor), repeat(times), repeat(rest));
+        if candidate.len() < best.len() {
+            best = candidate;
+        }
+    }
+    best
+}
+
+// the shortest signed step from one cell value to another, using wraparound at 256
+pub fn wrapping_delta(from: u8, to: u8) -> i32 {
+    to.wrapping_sub(from) as i8 as i32
+}
+
+// a program that outputs exactly `data`, printing from cell 0 and counting with cells 1 and 2,
+// which start and end at zero
+pub fn encode_bytes(data: &[u8]) -> String {
+    wrap_lines(&encode_runs(data))
+}
+
+pub fn encode_runs(data: &[u8]) -> String {
+    let mut code = String::new();
+    let mut current = 0u8;
+    let mut i = 0;
+    while i < data.len() {
+        let byte = data[i];
+        let run = data[i..].iter().take_while(|&&b| b == byte).count();
+        code += &adjust(wrapping_delta(current, byte));
+        current = byte;
+        if run <= RUN_LOOP_THRESHOLD {
+            code += &".".repeat(run);
+        } else {
+            // the counter only holds a byte, so long runs are printed in chunks
+            let mut left = run;
+            while left > 0 {
+                let chunk = left.min(255);
+                code += &format!(">{}[<.>-]<", adjust(chunk as u8 as i8 as i32));
+                left -= chunk;
+            }
+        }
+        i += run;
+    }
+    cancel_moves(code)
+}
+
+// search for a shorter printer: one multiplication loop seeds several cells with multiples of a
+// counter, then every byte is printed from whichever cell is cheapest to reach and adjust.
+// candidates are tried until the time budget runs out, and the shortest program wins
+pub fn encode_text_search(data: &[u8], budget: Duration) -> String {
+    let deadline = Instant::now() + budget;
+    let mut best = encode_runs(data);
+    let mut distinct: Vec<u8> = data.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+
+    'search: for attempt in 0.. {
+        for cells in 1..=MAX_SEARCH_CELLS.min(distinct.len()) {
+            let centres = cluster(data, &distinct, cells, attempt, &mut seed);
+            for counter in 2..=16 {
+                if Instant::now() >= deadline {
+                    break 'search;
+                }
+                let candidate = print_from_cells(data, &centres, counter);
+                if candidate.len() < best.len() {
+                    best = candidate;
+                }
+            }
+        }
+        // the first pass is deterministic, so a single byte value can't improve after it
+        if distinct.len() <= 1 {
+            break;
+        }
+    }
+    wrap_lines(&best)
+}
+
+// pick `cells` representative byte values with a few rounds of k-means; the first attempt seeds
+// from evenly spaced quantiles, later ones from random bytes of the text
+fn cluster(data: &[u8], distinct: &[u8], cells: usize, attempt: usize, seed: &mut u64) -> Vec<i32> {
+    let mut centres: Vec<i32> = (0..cells)
+        .map(|i| {
+            if attempt == 0 {
+                distinct[(2 * i + 1) * distinct.len() / (2 * cells)] as i32
+            } else {
+                *seed ^= *seed << 13;
+                *seed ^= *seed >> 7;
+                *seed ^= *seed << 17;
+                data[(*seed % data.len() as u64) as usize] as i32
+            }
+        })
+        .collect();
+    for _ in 0..8 {
+        let mut sums = vec![(0i64, 0i64); cells];
+        for &b in data {
+            let nearest = (0..cells)
+                .min_by_key(|&i| (centres[i] - b as i32).abs())
+                .unwrap_or(0);
+            sums[nearest].0 += b as i64;
+            sums[nearest].1 += 1;
+        }
+        for (centre, (sum, count)) in centres.iter_mut().zip(sums) {
+            if count > 0 {
+                *centre = (sum / count) as i32;
+            }
+        }
+    }
+    centres
+}
+
+// seed cells 1.. with the nearest multiples of `counter` to each centre, then print greedily
+fn print_from_cells(data: &[u8], centres: &[i32], counter: i32) -> String {
+    let factors: Vec<i32> = centres
+        .iter()
+        .map(|&c| (c + counter / 2) / counter)
+        .collect();
+    let mut code = repeat(counter) + "[";
+    for &factor in &factors {
+        code += ">";
+        code += &repeat(factor);
+    }
+    code += &"<".repeat(factors.len());
+    code += "-]";
+
+    let mut values: Vec<u8> = factors.iter().map(|&f| (f * counter) as u8).collect();
+    let mut pointer = 0usize; // tape index, the value cells being 1..=len
+    for &b in data {
+        let cost = |cell: usize, value: u8| {
+            pointer.abs_diff(cell + 1) as i32 + wrapping_delta(value, b).abs()
+        };
+        let cell = (0..values.len())
+            .min_by_key(|&i| cost(i, values[i]))
+            .unwrap_or(0);
+        let target = cell + 1;
+        if target > pointer {
+            code += &">".repeat(target - pointer);
+        } else {
+            code += &"<".repeat(pointer - target);
+        }
+        pointer = target;
+        code += &repeat(wrapping_delta(values[cell], b));
+        code += ".";
+        values[cell] = b;
+    }
+    code
+}
+
+// drop `<>` pairs left where one generated fragment ends and the next begins
+fn cancel_moves(mut code: String) -> String {
+    while code.contains("<>") {
+        code = code.replace("<>", "");
+    }
+    code
+}
+
+// break generated code into fixed-width lines
+fn wrap_lines(code: &str) -> String {
+    let chars: Vec<char> = code.chars().collect();
+    let mut out = String::new();
+    for line in chars.chunks(LINE_WIDTH) {
+        out.extend(line);
+        out.push('\n');
+    }
+    out
+}
+
+// write generated code to `-o file`, or stdout without one
+pub fn emit(code: &str, output: Option<&String>) -> i32 {
+    match output {
+        Some(path) => match fs::write(path, code) {
+            Ok(()) => 0,
+            Err(e) => {
+                println!("Unable to write {}: {}", path, e);
+                1
+            }
+        },
+        None => {
+            print!("{}", code);
+            0
+        }
+    }
+}
+
+pub fn text_main(name: &str, args: &[String]) -> i32 {
+    let usage = || {
+        println!("Usage: {} {}", name, TEXT_USAGE);
+        1
+    };
+    let mut text = None;
+    let mut output = None;
+    let mut search = false;
+    let mut budget = Duration::from_millis(DEFAULT_TIME_BUDGET_MS);
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-o" => match rest.next() {
+                Some(path) => output = Some(path),
+                None => return usage(),
+            },
+            "--search" => search = true,
+            "--time-budget" => match rest.next().and_then(|ms| ms.parse().ok()) {
+                Some(ms) => budget = Duration::from_millis(ms),
+                None => return usage(),
+            },
+            _ if text.is_none() => text = Some(arg),
+            _ => return usage(),
+        }
+    }
+    let text = match text {
+        Some(text) => text.as_bytes(),
+        None => return usage(),
+    };
+    let code = match search {
+        true => encode_text_search(text, budget),
+        false => encode_bytes(text),
+    };
+    emit(&code, output)
+}
+
+pub fn file_main(name: &str, args: &[String]) -> i32 {
+    let (input, output) = match args {
+        [input] => (input, None),
+        [input, flag, output] if flag == "-o" => (input, Some(output)),
+        _ => {
+            println!("Usage: {} {}", name, FILE_USAGE);
+            return 1;
+        }
+    };
+    match fs::read(input) {
+        Ok(data) => emit(&encode_bytes(&data), output),
+        Err(e) => {
+            println!("Unable to read {}: {}", input, e);
+            1
+        }
+    }
+}
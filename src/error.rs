@@ -0,0 +1,101 @@
+// what can go wrong using the library, as values a host can look at and recover from, rather
+// than panics that take its process down with them
+use std::{fmt, io};
+
+use crate::trap::Trap;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),         // reading or writing something outside the machine
+    Parse(ParseError),     // the text given couldn't be made sense of
+    Runtime(RuntimeError), // the program stopped itself with a trap
+}
+
+// text that wouldn't parse, with where in it the problem is when that's known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub line: Option<usize>, // 1-based
+    pub column: Option<usize>,
+}
+
+// a trap, along with how it reads against the source it happened in
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub trap: Trap,
+    pub description: String,
+}
+
+impl Error {
+    // a parse error at 1-based `line` and `column`
+    pub fn parse_at(line: usize, column: usize, message: impl Into<String>) -> Error {
+        Error::Parse(ParseError {
+            message: message.into(),
+            line: Some(line),
+            column: Some(column),
+        })
+    }
+
+    // a parse error about 1-based `line` as a whole
+    pub fn parse_line(line: usize, message: impl Into<String>) -> Error {
+        Error::Parse(ParseError {
+            message: message.into(),
+            line: Some(line),
+            column: None,
+        })
+    }
+
+    // a parse error that isn't about any one place
+    pub fn parse(message: impl Into<String>) -> Error {
+        Error::Parse(ParseError {
+            message: message.into(),
+            line: None,
+            column: None,
+        })
+    }
+
+    // the error for `trap`, described with lines and columns from `source`
+    pub fn runtime(trap: Trap, source: &[u8]) -> Error {
+        Error::Runtime(RuntimeError {
+            description: trap.describe(source),
+            trap,
+        })
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {}, column {}: {}", line, column, self.message)
+            }
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::Runtime(e) => write!(f, "{}", e.description),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
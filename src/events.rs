@@ -0,0 +1,57 @@
+// extension side effects reported to the embedder before they happen, so it can audit, mock
+// or refuse them
+use crate::{Cell, Interpreter};
+
+// something an extension op is about to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtensionEvent {
+    // a host function bound to `op` is about to be called on `cell`, which holds `value`
+    HostCall { op: char, cell: isize, value: Cell },
+}
+
+// what the embedder wants done about an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,         // go ahead as normal
+    Replace(Cell), // skip the extension and store this value instead
+    Deny,          // skip the extension and stop the run with a trap
+}
+
+pub type EventFn = Box<dyn FnMut(&ExtensionEvent) -> Verdict>;
+
+impl Interpreter {
+    // call `callback` before every extension side effect; its verdict decides what happens
+    pub fn on_extension_event(
+        &mut self,
+        callback: impl FnMut(&ExtensionEvent) -> Verdict + 'static,
+    ) {
+        self.extension_events = Some(Box::new(callback));
+    }
+
+    // run the host function bound to `op`, if the embedder lets it; false if the run trapped
+    pub fn call_host(&mut self, op: char) -> bool {
+        let value = self.memory.get_value();
+        let verdict = match &mut self.extension_events {
+            Some(callback) => callback(&ExtensionEvent::HostCall {
+                op,
+                cell: self.memory.idx as isize - self.memory.origin as isize,
+                value,
+            }),
+            None => Verdict::Allow,
+        };
+        match verdict {
+            Verdict::Allow => {
+                if let Some(function) = self.host_functions.get_mut(&op) {
+                    let value = function(value);
+                    self.memory.set_value(value);
+                }
+            }
+            Verdict::Replace(value) => self.memory.set_value(value),
+            Verdict::Deny => {
+                self.trap(format!("the host refused the extension op `{}`", op));
+                return false;
+            }
+        }
+        true
+    }
+}
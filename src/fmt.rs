@@ -0,0 +1,160 @@
+// `fmt`: lay a program out with one loop bracket per line and loop bodies indented. comments
+// stay where they were: text after code on a line stays after it, and text on lines of its
+// own keeps its own lines, indented with the code around it, so label markers, `lint allow`
+// directives and notes all come through
+use std::fs;
+
+use brainfuck_jit::{program::Program, split_source, Operations};
+
+use crate::encoder::emit;
+
+pub const USAGE: &str = "fmt [--indent N] [-w] [filename]";
+
+// how long a line of straight-line code gets before it's broken
+const LINE_WIDTH: usize = 80;
+
+// the program laid out
+pub fn format(program: &Program, indent: usize) -> String {
+    let mut out = String::new();
+    let mut depth = 0;
+    let mut line = String::new();
+    let mut comment = String::new();
+    let flush = |line: &mut String, depth: usize, out: &mut String| {
+        if !line.is_empty() {
+            out.push_str(&" ".repeat(depth * indent));
+            out.push_str(line);
+            out.push('\n');
+            line.clear();
+        }
+    };
+    // a run of comment text, once the code after it is reached
+    let place = |comment: &mut String, line: &mut String, depth: usize, out: &mut String| {
+        let mut pieces = comment.split('\n');
+        // what came after code on its line goes after it here, even once it's been flushed
+        let trailing = pieces.next().unwrap_or("").trim();
+        if !trailing.is_empty() {
+            match line.is_empty() && !out.is_empty() {
+                true => {
+                    out.pop();
+                    out.push(' ');
+                    out.push_str(trailing);
+                    out.push('\n');
+                }
+                false => {
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(trailing);
+                    flush(line, depth, out);
+                }
+            }
+        }
+        // then lines of their own, with one blank line kept where there were any; the last
+        // piece leads up to the code that follows, so an empty one isn't a blank line
+        let pieces: Vec<&str> = pieces.collect();
+        let mut blank = false;
+        for (i, piece) in pieces.iter().enumerate() {
+            let piece = piece.trim();
+            if piece.is_empty() {
+                blank |= i + 1 < pieces.len() && !(out.is_empty() && line.is_empty());
+                continue;
+            }
+            flush(line, depth, out);
+            if std::mem::take(&mut blank) && !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+            line.push_str(piece);
+            flush(line, depth, out);
+        }
+        if blank {
+            flush(line, depth, out);
+            if !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+        }
+        comment.clear();
+    };
+    for operation in program.operations.iter() {
+        if let Operations::Comment(c) = operation {
+            comment.push(*c);
+            continue;
+        }
+        place(&mut comment, &mut line, depth, &mut out);
+        match operation {
+            Operations::BracketLeft => {
+                flush(&mut line, depth, &mut out);
+                line.push('[');
+                flush(&mut line, depth, &mut out);
+                depth += 1;
+            }
+            Operations::BracketRight => {
+                flush(&mut line, depth, &mut out);
+                depth -= 1;
+                line.push(']');
+                flush(&mut line, depth, &mut out);
+            }
+            _ => {
+                if let Some(command) = operation.command() {
+                    if depth * indent + line.len() >= LINE_WIDTH {
+                        flush(&mut line, depth, &mut out);
+                    }
+                    line.push(command);
+                }
+            }
+        }
+    }
+    place(&mut comment, &mut line, depth, &mut out);
+    flush(&mut line, depth, &mut out);
+    while out.ends_with("\n\n") {
+        out.pop();
+    }
+    out
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let usage = || {
+        println!("Usage: {} {}", name, USAGE);
+        1
+    };
+    let (mut indent, mut write, mut files) = (2, false, Vec::new());
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--indent" => match rest.next().and_then(|n| n.parse().ok()) {
+                Some(n) => indent = n,
+                None => return usage(),
+            },
+            "-w" => write = true,
+            _ if arg.starts_with('-') => return usage(),
+            _ => files.push(arg),
+        }
+    }
+    let [filename] = files[..] else {
+        return usage();
+    };
+    let contents = match fs::read_to_string(filename) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Unable to read {}: {}", filename, e);
+            return 1;
+        }
+    };
+    let (source, inputs) = split_source(&contents);
+    let program = match Program::parse(source.as_bytes()) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("{}: {}", filename, e);
+            return 1;
+        }
+    };
+    // embedded input goes back on after the code, as it was
+    let mut formatted = format(&program, indent);
+    for input in inputs {
+        formatted.push('!');
+        formatted.push_str(input);
+    }
+    if !formatted.ends_with('\n') {
+        formatted.push('\n');
+    }
+    emit(&formatted, write.then_some(filename))
+}
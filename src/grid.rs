@@ -0,0 +1,110 @@
+// a planar tape for the brainfuck variants that assume one, e.g. maze and game demos: cells 0
+// up to `width * height` from the origin are laid out a row at a time, `<` and `>` move along
+// a row and two more ops move up and down a column. at an edge the pointer either wraps round
+// to the other side or the run traps
+use std::rc::Rc;
+
+use crate::{Interpreter, Memory, Operations};
+
+// the ops bound to moving up and down, unless the embedder picks others
+pub const UP: char = '^';
+pub const DOWN: char = 'v';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    pub wrap: bool, // go round to the far side at an edge rather than trap
+}
+
+impl Grid {
+    // a grid from `WIDTHxHEIGHT`, e.g. `80x25`
+    pub fn parse(text: &str) -> Option<Grid> {
+        let (width, height) = text.split_once('x')?;
+        let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+        (width > 0 && height > 0).then_some(Grid {
+            width,
+            height,
+            wrap: false,
+        })
+    }
+
+    pub fn cells(&self) -> usize {
+        self.width.saturating_mul(self.height)
+    }
+}
+
+impl Memory {
+    // the pointer's column and row, if it's on the grid
+    fn grid_position(&self, grid: Grid) -> Option<(isize, isize)> {
+        let at = self.idx as isize - self.origin as isize;
+        let width = grid.width as isize;
+        (0..grid.cells() as isize)
+            .contains(&at)
+            .then_some((at % width, at / width))
+    }
+
+    // move the pointer `dx` columns and `dy` rows from `(x, y)`; false if that would go off an
+    // edge that doesn't wrap
+    fn grid_move(&mut self, grid: Grid, (x, y): (isize, isize), dx: isize, dy: isize) -> bool {
+        let (width, height) = (grid.width as isize, grid.height as isize);
+        let (x, y) = (x + dx, y + dy);
+        let (x, y) = match grid.wrap {
+            true => (x.rem_euclid(width), y.rem_euclid(height)),
+            false if (0..width).contains(&x) && (0..height).contains(&y) => (x, y),
+            false => return false,
+        };
+        self.idx = self.origin + (y * width + x) as usize;
+        self.lowest = self.lowest.min(self.idx);
+        self.highest = self.highest.max(self.idx);
+        true
+    }
+}
+
+impl Interpreter {
+    // lay the tape out as `grid`, with `up` and `down` (characters the program would otherwise
+    // ignore) moving the pointer a row at a time
+    pub fn use_grid(&mut self, grid: Grid, up: char, down: char) {
+        let len = self.memory.origin + grid.cells();
+        if self.memory.bytearray.len() < len {
+            self.memory.resize(len);
+        }
+        for operation in Rc::make_mut(&mut self.operations).iter_mut() {
+            match *operation {
+                Operations::Comment(c) if c == up => *operation = Operations::Up(c),
+                Operations::Comment(c) if c == down => *operation = Operations::Down(c),
+                _ => {}
+            }
+        }
+        self.memory.grid = Some(grid);
+    }
+
+    // run a move on the grid, if there is one; false if the run trapped
+    pub(crate) fn move_on_grid(&mut self, operation: Operations) -> bool {
+        let Some(grid) = self.memory.grid else {
+            return true;
+        };
+        let (dx, dy, edge) = match operation {
+            Operations::MoveLeft => (-1, 0, "left"),
+            Operations::MoveRight => (1, 0, "right"),
+            Operations::Up(_) => (0, -1, "top"),
+            _ => (0, 1, "bottom"),
+        };
+        let Some(position) = self.memory.grid_position(grid) else {
+            let cell = self.pointer();
+            self.trap(format!(
+                "the pointer is on cell {}, which isn't on the grid",
+                cell
+            ));
+            return false;
+        };
+        if !self.memory.grid_move(grid, position, dx, dy) {
+            self.trap(format!(
+                "the pointer went off the {} edge of the grid",
+                edge
+            ));
+            return false;
+        }
+        true
+    }
+}
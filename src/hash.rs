@@ -0,0 +1,473 @@
+// `hash`: a sha-256 of just the program's commands, so a copy with different comments, layout
+// or embedded input hashes the same. with a key it's an hmac-sha256 instead: an authentication
+// tag, which only someone holding the same key can make or check, so whoever can check one can
+// forge one too. `--verify` compares against a published hash or tag, or with `--pubkey` checks
+// an ed25519 signature on the commands, which needs only the signer's public key
+use std::fs;
+
+use brainfuck_jit::{program::Program, split_source, Operations};
+
+use crate::ed25519;
+
+pub const USAGE: &str =
+    "hash [--key keyfile | --pubkey keyfile] [--verify hash-tag-or-signature-file] [--commands] [filename...]";
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// sha-512's: the same cube roots to 64 bits, over the first 80 primes
+const WIDE_ROUND_CONSTANTS: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+const BLOCK: usize = 64;
+const WIDE_BLOCK: usize = 128;
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    // the message, a 1 bit, zeros up to 8 bytes short of a whole block, then its length in bits
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % BLOCK != BLOCK - 8 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(BLOCK) {
+        let mut words = [0u32; 64];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..64 {
+            let (a, b) = (words[i - 15], words[i - 2]);
+            let s0 = a.rotate_right(7) ^ a.rotate_right(18) ^ (a >> 3);
+            let s1 = b.rotate_right(17) ^ b.rotate_right(19) ^ (b >> 10);
+            words[i] = words[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(words[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (&k, &w) in ROUND_CONSTANTS.iter().zip(&words) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choose = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choose)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            (h, g, f, e) = (g, f, e, d.wrapping_add(t1));
+            (d, c, b, a) = (c, b, a, t1.wrapping_add(t2));
+        }
+        for (word, added) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(added);
+        }
+    }
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+// fips 180-4's sha-512, for ed25519: sha-256 with 64 bit words, 80 rounds and 128 byte blocks
+pub fn sha512(data: &[u8]) -> [u8; 64] {
+    let mut state: [u64; 8] = [
+        0x6a09e667f3bcc908,
+        0xbb67ae8584caa73b,
+        0x3c6ef372fe94f82b,
+        0xa54ff53a5f1d36f1,
+        0x510e527fade682d1,
+        0x9b05688c2b3e6c1f,
+        0x1f83d9abfb41bd6b,
+        0x5be0cd19137e2179,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % WIDE_BLOCK != WIDE_BLOCK - 16 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u128 * 8).to_be_bytes());
+
+    for block in message.chunks(WIDE_BLOCK) {
+        let mut words = [0u64; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(8)) {
+            *word = u64::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..80 {
+            let (a, b) = (words[i - 15], words[i - 2]);
+            let s0 = a.rotate_right(1) ^ a.rotate_right(8) ^ (a >> 7);
+            let s1 = b.rotate_right(19) ^ b.rotate_right(61) ^ (b >> 6);
+            words[i] = words[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(words[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (&k, &w) in WIDE_ROUND_CONSTANTS.iter().zip(&words) {
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let choose = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choose)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            (h, g, f, e) = (g, f, e, d.wrapping_add(t1));
+            (d, c, b, a) = (c, b, a, t1.wrapping_add(t2));
+        }
+        for (word, added) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(added);
+        }
+    }
+    let mut digest = [0; 64];
+    for (bytes, word) in digest.chunks_mut(8).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+// rfc 2104's keyed hash, a message authentication code
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut padded = [0u8; BLOCK];
+    match key.len() > BLOCK {
+        true => padded[..32].copy_from_slice(&sha256(key)),
+        false => padded[..key.len()].copy_from_slice(key),
+    }
+    let keyed = |pad: u8, rest: &[u8]| {
+        let mut message: Vec<u8> = padded.iter().map(|b| b ^ pad).collect();
+        message.extend_from_slice(rest);
+        sha256(&message)
+    };
+    keyed(0x5c, &keyed(0x36, data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// a key or signature file: the first word in hex, as most tools print one, or the raw bytes
+fn key_bytes<const N: usize>(contents: &[u8]) -> Option<[u8; N]> {
+    let text = String::from_utf8_lossy(contents);
+    let word = text.split_whitespace().next().unwrap_or("");
+    match unhex(word).and_then(|bytes| bytes.try_into().ok()) {
+        Some(bytes) => Some(bytes),
+        None => contents.try_into().ok(),
+    }
+}
+
+// the program as it's hashed: its commands and nothing else
+pub fn canonical(program: &Program) -> String {
+    program
+        .operations
+        .iter()
+        .filter_map(Operations::command)
+        .collect()
+}
+
+// whether two hex digests match, looking at every byte so the time taken doesn't say how
+// much of a guess was right
+fn digests_match(expected: &str, actual: &str) -> bool {
+    let (expected, actual) = (expected.to_ascii_lowercase(), actual.as_bytes());
+    expected.len() == actual.len()
+        && expected
+            .bytes()
+            .zip(actual)
+            .fold(0, |differences, (a, b)| differences | (a ^ b))
+            == 0
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let usage = || {
+        println!("Usage: {} {}", name, USAGE);
+        1
+    };
+    let (mut key, mut public_key, mut verify) = (None, None, None);
+    let (mut commands_only, mut files) = (false, Vec::new());
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        let slot = match arg.as_str() {
+            "--key" => &mut key,
+            "--pubkey" => &mut public_key,
+            "--verify" => &mut verify,
+            "--commands" => {
+                commands_only = true;
+                continue;
+            }
+            _ if arg.starts_with('-') => return usage(),
+            _ => {
+                files.push(arg);
+                continue;
+            }
+        };
+        match rest.next() {
+            Some(path) => *slot = Some(path),
+            None => return usage(),
+        }
+    }
+    let keyed = key.is_some() || public_key.is_some();
+    if files.is_empty()
+        || (verify.is_some() && files.len() != 1)
+        || (key.is_some() && public_key.is_some())
+        || (public_key.is_some() && verify.is_none())
+        || (commands_only && (keyed || verify.is_some()))
+    {
+        return usage();
+    }
+    let read =
+        |path: &String| fs::read(path).map_err(|e| println!("Unable to read {}: {}", path, e));
+    let Ok(key) = key.map(read).transpose() else {
+        return 1;
+    };
+    let Ok(public_key) = public_key.map(read).transpose() else {
+        return 1;
+    };
+    let public_key = match public_key.map(|contents| key_bytes::<32>(&contents)) {
+        Some(None) => {
+            println!("The public key isn't 32 bytes, in hex or raw");
+            return 1;
+        }
+        public_key => public_key.flatten(),
+    };
+    let mut status = 0;
+    for path in files {
+        let Ok(contents) = read(path) else {
+            status = 1;
+            continue;
+        };
+        let contents = String::from_utf8_lossy(&contents);
+        let (source, _) = split_source(&contents);
+        let program = match Program::parse(source.as_bytes()) {
+            Ok(program) => program,
+            Err(e) => {
+                println!("{}: {}", path, e);
+                status = 1;
+                continue;
+            }
+        };
+        let commands = canonical(&program);
+        if commands_only {
+            println!("{}", commands);
+            continue;
+        }
+        let digest = hex(&match &key {
+            Some(key) => hmac_sha256(key, commands.as_bytes()),
+            None => sha256(commands.as_bytes()),
+        });
+        let Some(published) = verify else {
+            println!("{}  {}", digest, path);
+            continue;
+        };
+        let Ok(published) = read(published) else {
+            return 1;
+        };
+        let matches = match &public_key {
+            Some(public_key) => match key_bytes::<64>(&published) {
+                Some(signature) => ed25519::verify(public_key, commands.as_bytes(), &signature),
+                None => {
+                    println!("The signature isn't 64 bytes, in hex or raw");
+                    return 1;
+                }
+            },
+            None => {
+                // the first word of the file, as `hash` writes it
+                let published = String::from_utf8_lossy(&published);
+                let expected = published.split_whitespace().next().unwrap_or("");
+                digests_match(expected, &digest)
+            }
+        };
+        match matches {
+            true => println!("{}: ok", path),
+            false => {
+                println!("{}: doesn't match", path);
+                status = 1;
+            }
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the one block and two block messages from rfc 6234 section 8.5, and fips 180-4's million a's
+    #[test]
+    fn sha256_known_answers() {
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(&sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            hex(&sha256(&[b'a'; 1_000_000])),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn sha512_known_answers() {
+        assert_eq!(
+            hex(&sha512(b"abc")),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        assert_eq!(
+            hex(&sha512(
+                b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+                  hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"
+            )),
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+             501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+        );
+    }
+
+    // rfc 4231's test cases 1, 2 and 6: a short key, one shorter than the hash, and one longer
+    // than a block
+    #[test]
+    fn hmac_known_answers() {
+        assert_eq!(
+            hex(&hmac_sha256(&[0x0b; 20], b"Hi There")),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn key_files() {
+        let key = "03a107bff3ce10be1d70dd18e74bc09967e4d6309ba50d5f1ddc8664125531b8";
+        let bytes = unhex(key).unwrap();
+        assert_eq!(
+            key_bytes::<32>(format!("{}  key\n", key).as_bytes()),
+            bytes.clone().try_into().ok()
+        );
+        assert_eq!(key_bytes::<32>(&bytes), bytes.clone().try_into().ok());
+        assert_eq!(key_bytes::<32>(&bytes[1..]), None);
+        assert_eq!(unhex("0"), None);
+        assert_eq!(unhex("zz"), None);
+    }
+}
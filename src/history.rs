@@ -0,0 +1,254 @@
+// recording each step so a machine can be wound back, for time-travel debugging; the newest
+// steps are kept in memory, and with a file the older ones spill to it. the file is a ring
+// of fixed-size records, so it never grows past its cap: the oldest steps are lost instead
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    mem,
+    path::Path,
+};
+
+use crate::{Cell, Interpreter};
+
+// what one step changed, enough to undo it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Undo {
+    pub index: usize,   // the instruction that ran
+    pub pointer: isize, // the cell the pointer was on, counting from the origin
+    pub value: Cell,    // what the cell under it held
+    pub steps: usize,
+    pub depth: usize,
+    pub output_len: usize,
+    pub input: Option<u8>, // the byte it read, if it read one
+}
+
+const RECORD_SIZE: usize = 56;
+
+// records moved to the file at once, so spilling isn't a write per step
+const SPILL_CHUNK: usize = 4096;
+
+impl Undo {
+    fn to_bytes(self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        let words = [
+            self.index as u64,
+            self.pointer as u64,
+            self.steps as u64,
+            self.depth as u64,
+            self.output_len as u64,
+            self.value,
+        ];
+        for (chunk, word) in bytes.chunks_mut(8).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        if let Some(byte) = self.input {
+            bytes[48] = 1;
+            bytes[49] = byte;
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; RECORD_SIZE]) -> Undo {
+        let word = |i: usize| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap());
+        Undo {
+            index: word(0) as usize,
+            pointer: word(1) as isize,
+            steps: word(2) as usize,
+            depth: word(3) as usize,
+            output_len: word(4) as usize,
+            value: word(5),
+            input: (bytes[48] == 1).then_some(bytes[49]),
+        }
+    }
+}
+
+// the ring of records in a file
+struct Spill {
+    file: File,
+    capacity: u64, // in records
+    start: u64,    // the slot of the oldest record
+    len: u64,
+}
+
+impl Spill {
+    // add `records` after the newest, overwriting the oldest once the ring is full
+    fn push(&mut self, records: &[Undo]) -> io::Result<()> {
+        let mut pending = Vec::new(); // records for consecutive slots from `first`
+        let mut first = 0;
+        for record in records {
+            let slot = (self.start + self.len) % self.capacity;
+            if self.len == self.capacity {
+                self.start = (self.start + 1) % self.capacity;
+            } else {
+                self.len += 1;
+            }
+            if slot == 0 && !pending.is_empty() {
+                self.write_at(first, &pending)?;
+                pending.clear();
+            }
+            if pending.is_empty() {
+                first = slot;
+            }
+            pending.extend_from_slice(&record.to_bytes());
+        }
+        self.write_at(first, &pending)
+    }
+
+    fn write_at(&mut self, slot: u64, bytes: &[u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(slot * RECORD_SIZE as u64))?;
+        self.file.write_all(bytes)
+    }
+
+    // take the newest record off
+    fn pop(&mut self) -> io::Result<Option<Undo>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        let slot = (self.start + self.len) % self.capacity;
+        let mut bytes = [0; RECORD_SIZE];
+        self.file.seek(SeekFrom::Start(slot * RECORD_SIZE as u64))?;
+        self.file.read_exact(&mut bytes)?;
+        Ok(Some(Undo::from_bytes(&bytes)))
+    }
+}
+
+// the steps a machine has taken, newest last
+pub struct History {
+    recent: VecDeque<Undo>,
+    limit: usize, // steps kept in memory
+    spill: Option<Spill>,
+    lost: bool, // whether steps have been forgotten to stay within the caps
+}
+
+impl History {
+    // keep up to `limit` steps, in memory
+    pub fn new(limit: usize) -> History {
+        History {
+            recent: VecDeque::new(),
+            limit: limit.max(1),
+            spill: None,
+            lost: false,
+        }
+    }
+
+    // keep steps past the memory limit in the file at `path`, up to `cap` bytes of it
+    pub fn spill_to(mut self, path: &Path, cap: u64) -> io::Result<History> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        self.spill = Some(Spill {
+            file,
+            capacity: (cap / RECORD_SIZE as u64).max(1),
+            start: 0,
+            len: 0,
+        });
+        Ok(self)
+    }
+
+    // how many steps can be undone
+    pub fn len(&self) -> usize {
+        self.recent.len() + self.spill.as_ref().map_or(0, |spill| spill.len as usize)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // the steps kept in memory, oldest first; the newest are the last ones taken
+    pub fn recent(&self) -> impl Iterator<Item = &Undo> {
+        self.recent.iter()
+    }
+
+    // whether older steps had to be forgotten, so winding back stops short of the start
+    pub fn lost(&self) -> bool {
+        self.lost
+    }
+
+    fn push(&mut self, undo: Undo) -> io::Result<()> {
+        self.recent.push_back(undo);
+        let over = match self.spill {
+            Some(_) => SPILL_CHUNK,
+            None => 0,
+        };
+        if self.recent.len() > self.limit + over {
+            let oldest: Vec<Undo> = self
+                .recent
+                .drain(..self.recent.len() - self.limit)
+                .collect();
+            match &mut self.spill {
+                Some(spill) => {
+                    self.lost |= spill.len + oldest.len() as u64 > spill.capacity;
+                    spill.push(&oldest)?;
+                }
+                None => self.lost = true,
+            }
+        }
+        Ok(())
+    }
+
+    fn pop(&mut self) -> io::Result<Option<Undo>> {
+        match (self.recent.pop_back(), &mut self.spill) {
+            (Some(undo), _) => Ok(Some(undo)),
+            (None, Some(spill)) => spill.pop(),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+impl Interpreter {
+    // what undoing the step about to run will take
+    pub(crate) fn undo_point(&self) -> Undo {
+        Undo {
+            index: self.idx,
+            pointer: self.pointer(),
+            value: self.memory.bytearray[self.memory.idx],
+            steps: self.steps,
+            depth: self.depth,
+            output_len: self.output_len,
+            input: None,
+        }
+    }
+
+    // note the step that just ran, taken from `undo_point` before it
+    pub(crate) fn remember(&mut self, mut undo: Undo, input_len: usize) {
+        if self.input_len > input_len {
+            undo.input = Some(self.last_input);
+        }
+        if let Some(history) = &mut self.history {
+            if let Err(e) = history.push(undo) {
+                self.trap(format!("unable to write the history: {}", e));
+            }
+        }
+    }
+
+    // undo the last step, including a trap it hit; false once there's no history left. what
+    // it wrote has been written, but what it read will be read again
+    pub fn step_back(&mut self) -> io::Result<bool> {
+        let undo = match &mut self.history {
+            Some(history) => match history.pop()? {
+                Some(undo) => undo,
+                None => return Ok(false),
+            },
+            None => return Ok(false),
+        };
+        self.idx = undo.index;
+        self.memory.idx = (self.memory.origin as isize + undo.pointer) as usize;
+        self.memory.set_value(undo.value);
+        self.steps = undo.steps;
+        self.depth = undo.depth;
+        self.output_len = undo.output_len;
+        if let Some(byte) = undo.input {
+            self.input_len -= 1;
+            let rest = mem::replace(&mut self.input, Box::new(io::empty()));
+            self.input = Box::new(io::Cursor::new([byte]).chain(rest));
+        }
+        self.trap = None;
+        self.halt = None;
+        Ok(true)
+    }
+}
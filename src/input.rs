@@ -0,0 +1,131 @@
+// transformations applied to the input stream before `,` sees it
+use std::io::{self, Read};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    #[default]
+    Unchanged,
+    Upper,
+    Lower,
+}
+
+// which transformations to apply; the default leaves input alone
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InputOptions {
+    pub hex: bool,           // decode pairs of hex digits, skipping whitespace
+    pub crlf: bool,          // turn `\r\n` into `\n`
+    pub strip_newline: bool, // drop one newline at the very end of the input
+    pub case: Case,
+}
+
+impl InputOptions {
+    pub fn is_identity(&self) -> bool {
+        !self.hex && !self.crlf && !self.strip_newline && self.case == Case::Unchanged
+    }
+}
+
+// a reader applying `InputOptions` one byte at a time, so interactive input still flows;
+// only a newline (or carriage return) waits to see what follows it
+pub struct InputFilter<R> {
+    inner: R,
+    options: InputOptions,
+    pushed_back: Vec<Option<u8>>,
+}
+
+impl<R: Read> InputFilter<R> {
+    pub fn new(inner: R, options: InputOptions) -> InputFilter<R> {
+        InputFilter {
+            inner,
+            options,
+            pushed_back: Vec::new(),
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0u8];
+        match self.inner.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    // the next byte of the source, hex-decoded if asked
+    fn raw(&mut self) -> io::Result<Option<u8>> {
+        if let Some(byte) = self.pushed_back.pop() {
+            return Ok(byte);
+        }
+        if !self.options.hex {
+            return self.read_byte();
+        }
+        let mut digits = Vec::new();
+        while digits.len() < 2 {
+            match self.read_byte()? {
+                None if digits.is_empty() => return Ok(None),
+                None => return Err(invalid("odd number of hex digits")),
+                Some(c) if c.is_ascii_whitespace() => {}
+                Some(c) => match (c as char).to_digit(16) {
+                    Some(digit) => digits.push(digit as u8),
+                    None => return Err(invalid("not a hex digit")),
+                },
+            }
+        }
+        Ok(Some(digits[0] << 4 | digits[1]))
+    }
+
+    fn next(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = self.raw()?;
+        if self.options.crlf && byte == Some(b'\r') {
+            let next = self.raw()?;
+            match next {
+                Some(b'\n') => byte = next,
+                _ => self.pushed_back.push(next),
+            }
+        }
+        if self.options.strip_newline && matches!(byte, Some(b'\n' | b'\r')) {
+            // a final `\n`, `\r` or `\r\n` is dropped
+            let mut next = self.raw()?;
+            let mut second = None;
+            if byte == Some(b'\r') && next == Some(b'\n') {
+                second = next;
+                next = self.raw()?;
+            }
+            match next {
+                None => byte = None,
+                Some(_) => {
+                    self.pushed_back.push(next);
+                    if second.is_some() {
+                        self.pushed_back.push(second);
+                    }
+                }
+            }
+        }
+        Ok(byte.map(|b| match self.options.case {
+            Case::Unchanged => b,
+            Case::Upper => b.to_ascii_uppercase(),
+            Case::Lower => b.to_ascii_lowercase(),
+        }))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("bad hex input: {}", message),
+    )
+}
+
+// one byte per read, so nothing blocks waiting for input the program hasn't asked for
+impl<R: Read> Read for InputFilter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.next()? {
+            Some(byte) => {
+                buf[0] = byte;
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+}
@@ -0,0 +1,455 @@
+// a compact form of the program for plain runs: runs of `+-` and of `<` or `>` become one
+// instruction each and loops jump by instruction, so long straight-line stretches cost one
+// dispatch instead of one per character
+use std::{fmt, rc::Rc};
+
+use crate::{Cell, Interpreter, Operations};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ir {
+    Add(i64),     // change the cell by this much, wrapping
+    Move(isize),  // move the pointer this many cells, all in one direction
+    Open(usize),  // a `[`, with the instruction number of its `]`
+    Close(usize), // a `]`, with the instruction number of its `[`
+    SetZero(i64), // `[-]` or `[+]`: count the cell to zero by this, all at once
+    Mul(Box<MulLoop>),
+    Scan(isize), // `[>]`, `[<<]` and so on: move this far at a time until on a zero cell
+    Op,          // anything else, run by the interpreter as it is
+}
+
+impl fmt::Display for Ir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ir::Add(delta) => write!(f, "Add({:+})", delta),
+            Ir::Move(by) => write!(f, "Move({:+})", by),
+            Ir::Open(end) => write!(f, "Open(end={})", end),
+            Ir::Close(start) => write!(f, "Close(start={})", start),
+            Ir::SetZero(_) => write!(f, "SetZero"),
+            Ir::Scan(by) => write!(f, "Scan({:+})", by),
+            Ir::Mul(mul) => {
+                let targets: Vec<String> = mul
+                    .targets
+                    .iter()
+                    .map(|(offset, factor)| format!("dst={:+}, factor={}", offset, factor))
+                    .collect();
+                write!(f, "Mul({})", targets.join("; "))
+            }
+            Ir::Op => write!(f, "Op"),
+        }
+    }
+}
+
+// a loop that only adds to cells around the pointer and ends back where it started, so all
+// its times round can be done at once: `[->+<]` adds the cell to the next one, for instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MulLoop {
+    pub step: i64,                  // what each time round adds to the cell being counted
+    pub targets: Vec<(isize, i64)>, // the other cells, by offset, and what each time adds
+    pub reach: (isize, isize),      // the furthest left and right the pointer goes
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub ir: Ir,
+    pub index: usize, // the first operation it was made from
+    pub last: usize,  // and the last
+    pub steps: usize, // how many commands it stands for
+}
+
+// the step of a loop whose body is a single `-` or `+`, which just clears the cell
+fn clear_step(body: &[Instruction]) -> Option<i64> {
+    match body {
+        [Instruction {
+            ir: Ir::Add(step @ (-1 | 1)),
+            steps: 1,
+            ..
+        }] => Some(*step),
+        _ => None,
+    }
+}
+
+// `body` as a multiply loop, if that's what it is
+fn mul_loop(body: &[Instruction]) -> Option<MulLoop> {
+    let (mut offset, mut reach) = (0isize, (0, 0));
+    let mut adds: Vec<(isize, i64)> = Vec::new();
+    for instruction in body {
+        match instruction.ir {
+            Ir::Move(by) => {
+                offset += by;
+                reach = (reach.0.min(offset), reach.1.max(offset));
+            }
+            Ir::Add(delta) => match adds.iter_mut().find(|(at, _)| *at == offset) {
+                Some((_, total)) => *total += delta,
+                None => adds.push((offset, delta)),
+            },
+            _ => return None,
+        }
+    }
+    let step = adds.iter().find(|(at, _)| *at == 0).map(|&(_, step)| step);
+    match (offset, step) {
+        (0, Some(step @ (-1 | 1))) => Some(MulLoop {
+            step,
+            targets: adds.into_iter().filter(|&(at, _)| at != 0).collect(),
+            reach,
+        }),
+        _ => None,
+    }
+}
+
+// the step of a loop whose body is a single move, which goes looking for a zero cell
+fn scan_step(body: &[Instruction]) -> Option<isize> {
+    match body {
+        [Instruction {
+            ir: Ir::Move(by), ..
+        }] => Some(*by),
+        _ => None,
+    }
+}
+
+// a loop with `body` as a single instruction, if it's one that can be done in one go
+fn whole_loop(body: &[Instruction]) -> Option<Ir> {
+    clear_step(body)
+        .map(Ir::SetZero)
+        .or_else(|| scan_step(body).map(Ir::Scan))
+        .or_else(|| mul_loop(body).map(|mul| Ir::Mul(Box::new(mul))))
+}
+
+// a machine's compiled form, kept between runs so picking a run back up, or running a fragment
+// appended to the program, doesn't compile the whole program again. holding on to the
+// operations it came from means changing them goes through `Rc::make_mut` to a new copy, which
+// is how it's known to be out of date
+pub(crate) struct Compiled {
+    operations: Rc<Vec<Operations>>,
+    code: Rc<Vec<Instruction>>,
+}
+
+// the instructions for `operations`; comments are left out
+pub fn compile(operations: &[Operations]) -> Vec<Instruction> {
+    let mut code: Vec<Instruction> = Vec::new();
+    let mut open = Vec::new(); // `[`s not yet closed, by instruction number
+    for (index, operation) in operations.iter().enumerate() {
+        let ir = match operation {
+            Operations::Add => Ir::Add(1),
+            Operations::Subtract => Ir::Add(-1),
+            Operations::MoveRight => Ir::Move(1),
+            Operations::MoveLeft => Ir::Move(-1),
+            Operations::BracketLeft => {
+                open.push(code.len());
+                Ir::Open(0)
+            }
+            Operations::BracketRight => match open.pop() {
+                Some(start) => match whole_loop(&code[start + 1..]) {
+                    Some(ir) => {
+                        let steps: usize = code[start..].iter().map(|i| i.steps).sum();
+                        code.truncate(start + 1);
+                        code[start].ir = ir;
+                        code[start].last = index;
+                        code[start].steps = steps + 1;
+                        continue;
+                    }
+                    None => {
+                        code[start].ir = Ir::Open(code.len());
+                        Ir::Close(start)
+                    }
+                },
+                None => Ir::Close(code.len()), // unmatched: carry on past it
+            },
+            Operations::Comment(_) => continue,
+            _ => Ir::Op,
+        };
+        // fold into the instruction before, if it's the same kind
+        if let Some(last) = code.last_mut() {
+            let folded = match (&last.ir, &ir) {
+                (Ir::Add(a), Ir::Add(b)) => Some(Ir::Add(a + b)),
+                (Ir::Move(a), Ir::Move(b)) if (*a > 0) == (*b > 0) => Some(Ir::Move(a + b)),
+                _ => None,
+            };
+            if let Some(folded) = folded {
+                last.ir = folded;
+                last.last = index;
+                last.steps += 1;
+                continue;
+            }
+        }
+        code.push(Instruction {
+            ir,
+            index,
+            last: index,
+            steps: 1,
+        });
+    }
+    // an unmatched `[` skips to the end
+    for start in open {
+        code[start].ir = Ir::Open(code.len());
+    }
+    code
+}
+
+impl Interpreter {
+    // run to the end through the compiled form; only for runs nothing is watching step by
+    // step, since a fused instruction is one step as far as the machine can tell
+    pub(crate) fn run_compiled(&mut self) {
+        let code = self.compiled_code();
+        // a run picked up partway through a fused instruction finishes it a step at a time
+        let mut pc = loop {
+            match code.binary_search_by_key(&self.idx, |instruction| instruction.index) {
+                Ok(pc) => break pc,
+                Err(_) if self.idx >= self.operations.len() => return,
+                Err(_) => {
+                    if self.step().is_err() {
+                        return;
+                    }
+                }
+            }
+        };
+        while let Some(instruction) = code.get(pc) {
+            if self.max_steps.is_some() || self.time_limit.is_some() {
+                // so a trap says where the run had got to
+                self.idx = instruction.index;
+                if self.check_limits().is_err() {
+                    return;
+                }
+            }
+            let steps = match instruction.ir {
+                Ir::Add(delta) => {
+                    let slack = instruction.steps as i128;
+                    if !self.memory.stays_in_range(0, delta as i128, slack)
+                        || !self.within_budget(instruction.steps)
+                    {
+                        // it could overflow partway, which is for the policy to settle, or
+                        // use up the steps left partway
+                        if !self.run_slowly(instruction) {
+                            return;
+                        }
+                        0 // counted as they ran
+                    } else {
+                        self.memory.add(delta);
+                        instruction.steps
+                    }
+                }
+                Ir::Move(by) => {
+                    if !self.within_budget(instruction.steps) || !self.memory.shift(by) {
+                        // it goes off an end of the tape, which is for the policy to settle
+                        if !self.run_slowly(instruction) {
+                            return;
+                        }
+                        0 // counted as they ran
+                    } else {
+                        instruction.steps
+                    }
+                }
+                Ir::Open(end) => {
+                    if self.memory.get_value() == 0 {
+                        pc = end;
+                    } else {
+                        self.depth += 1;
+                    }
+                    1
+                }
+                Ir::Close(start) => {
+                    if self.memory.get_value() != 0 {
+                        pc = start;
+                    } else {
+                        self.depth = self.depth.saturating_sub(1);
+                    }
+                    1
+                }
+                Ir::SetZero(step) => {
+                    let times = self.memory.times_round(step);
+                    if !self
+                        .memory
+                        .stays_in_range(0, step as i128 * times as i128, 0)
+                        || !self.within_budget(loop_steps(instruction, times))
+                    {
+                        if !self.run_slowly(instruction) {
+                            return;
+                        }
+                        0 // counted as they ran
+                    } else {
+                        self.memory.set_value(0);
+                        loop_steps(instruction, times)
+                    }
+                }
+                Ir::Mul(ref mul) => {
+                    let times = self.memory.times_round(mul.step);
+                    if (times > 0 && !self.mul_fits(mul, times, instruction.steps))
+                        || !self.within_budget(loop_steps(instruction, times))
+                    {
+                        // it would go off an end of the tape, or could overflow a cell
+                        if !self.run_slowly(instruction) {
+                            return;
+                        }
+                        0 // counted as they ran
+                    } else {
+                        if times > 0 {
+                            for &(offset, factor) in &mul.targets {
+                                self.memory.add_times(offset, factor, times);
+                            }
+                            self.memory.set_value(0);
+                            self.memory.visit(mul.reach);
+                        }
+                        loop_steps(instruction, times)
+                    }
+                }
+                Ir::Scan(by) => match self.memory.scan_length(by) {
+                    Some(times) if self.within_budget(loop_steps(instruction, times as Cell)) => {
+                        self.memory.shift(times as isize * by);
+                        loop_steps(instruction, times as Cell)
+                    }
+                    _ => {
+                        // there's no zero before the end of the tape, so it's for the policy,
+                        // or not enough steps are left to get there
+                        if !self.run_slowly(instruction) {
+                            return;
+                        }
+                        0 // counted as they ran
+                    }
+                },
+                Ir::Op => {
+                    self.idx = instruction.index;
+                    // a trap or closed output moves the machine to the end
+                    self.execute();
+                    if self.idx != instruction.index + 1 {
+                        return;
+                    }
+                    0 // counted by `execute`
+                }
+            };
+            self.steps = self.steps.saturating_add(steps);
+            self.ran_last = instruction.index;
+            pc += 1;
+            self.beat(false);
+        }
+        self.idx = self.operations.len();
+    }
+
+    // the compiled form of the program as it stands, from the last run if nothing has changed
+    fn compiled_code(&mut self) -> Rc<Vec<Instruction>> {
+        match self.compiled.as_ref() {
+            Some(compiled) if Rc::ptr_eq(&compiled.operations, &self.operations) => {
+                Rc::clone(&compiled.code)
+            }
+            _ => {
+                let code = Rc::new(compile(&self.operations));
+                self.keep_compiled(Rc::clone(&code));
+                code
+            }
+        }
+    }
+
+    fn keep_compiled(&mut self, code: Rc<Vec<Instruction>>) {
+        self.compiled = Some(Compiled {
+            operations: Rc::clone(&self.operations),
+            code,
+        });
+    }
+
+    // take the compiled form of the operations as they stand away, letting go of them so
+    // they can be appended to where they are rather than copied
+    pub(crate) fn take_compiled(&mut self) -> Option<Rc<Vec<Instruction>>> {
+        let compiled = self.compiled.take()?;
+        Rc::ptr_eq(&compiled.operations, &self.operations).then_some(compiled.code)
+    }
+
+    // compile operations from `start` on, just appended as a whole fragment whose brackets
+    // match among themselves, onto the end of `kept`, the compiled form of the ones before
+    // them; without it, the next run compiles from scratch
+    pub(crate) fn compile_appended(&mut self, kept: Option<Rc<Vec<Instruction>>>, start: usize) {
+        let Some(code) = kept else {
+            return;
+        };
+        let mut code = Rc::try_unwrap(code).unwrap_or_else(|code| (*code).clone());
+        let base = code.len();
+        code.extend(
+            compile(&self.operations[start..])
+                .into_iter()
+                .map(|mut added| {
+                    added.index += start;
+                    added.last += start;
+                    added.ir = match added.ir {
+                        Ir::Open(end) => Ir::Open(end + base),
+                        Ir::Close(open) => Ir::Close(open + base),
+                        ir => ir,
+                    };
+                    added
+                }),
+        );
+        self.keep_compiled(Rc::new(code));
+    }
+
+    // whether a multiply loop going round `times` stays on the tape and, unless cells wrap,
+    // keeps every cell it touches in range, going by the loop's `steps` for what a time round
+    // could do before it's done
+    fn mul_fits(&self, mul: &MulLoop, times: Cell, steps: usize) -> bool {
+        let memory = &self.memory;
+        memory.reaches(mul.reach)
+            && memory.stays_in_range(0, mul.step as i128 * times as i128, steps as i128)
+            && mul.targets.iter().all(|&(offset, factor)| {
+                memory.stays_in_range(offset, factor as i128 * times as i128, steps as i128)
+            })
+    }
+
+    // run the operations `instruction` was made from a step at a time; false if it trapped
+    fn run_slowly(&mut self, instruction: &Instruction) -> bool {
+        self.idx = instruction.index;
+        while self.idx <= instruction.last {
+            if self.step().is_err() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// the steps a loop done in one go would have taken going round `times`: its `[`, then its
+// body and `]` each time
+fn loop_steps(instruction: &Instruction, times: Cell) -> usize {
+    let times = usize::try_from(times).unwrap_or(usize::MAX);
+    (instruction.steps - 1)
+        .saturating_mul(times)
+        .saturating_add(1)
+}
+
+// an optimization applied to the program, for `--explain-opts`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub place: String,         // where the matched code starts
+    pub pattern: &'static str, // what kind of code it is
+    pub matched: String,       // the commands it replaces
+    pub replacement: Ir,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} `{}` → {}",
+            self.place, self.pattern, self.matched, self.replacement
+        )
+    }
+}
+
+impl Interpreter {
+    // each optimization a plain run would make, in program order
+    pub fn explain(&self) -> Vec<Explanation> {
+        compile(&self.operations)
+            .into_iter()
+            .filter(|instruction| instruction.steps > 1)
+            .map(|instruction| Explanation {
+                place: self.place(instruction.index),
+                pattern: match instruction.ir {
+                    Ir::Add(_) => "run of cell changes",
+                    Ir::SetZero(_) => "clear loop",
+                    Ir::Mul(_) => "multiply loop",
+                    Ir::Scan(_) => "scan loop",
+                    _ => "run of pointer moves",
+                },
+                matched: self.operations[instruction.index..=instruction.last]
+                    .iter()
+                    .filter_map(Operations::command)
+                    .collect(),
+                replacement: instruction.ir.clone(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,1072 @@
+// the interpreter itself, for embedding; the `brainfuck-jit` binary is a command line over it
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    iter, mem,
+    ops::Range,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+pub mod builder;
+pub mod error;
+pub mod events;
+pub mod grid;
+pub mod history;
+pub mod input;
+pub mod ir;
+pub mod observer;
+pub mod opmap;
+#[cfg(all(feature = "plugins", unix))]
+pub mod plugins;
+pub mod pool;
+pub mod program;
+pub mod progress;
+pub mod quine;
+pub mod result;
+pub mod session;
+pub mod step;
+pub mod store;
+pub mod stream;
+pub mod taint;
+pub mod testkit;
+pub mod timeline;
+pub mod trace;
+pub mod trap;
+pub mod usage;
+pub mod values;
+
+use builder::HostFunction;
+use error::Error;
+use events::EventFn;
+use grid::Grid;
+use history::History;
+use ir::Compiled;
+use observer::Observer;
+use program::{jump_table, Label, Program, Span};
+use progress::HeartbeatHook;
+use step::RunStatus;
+use store::ProgramStore;
+use taint::Taint;
+use timeline::{Access, Timeline};
+use trace::LoopTrace;
+use trap::{LoopRef, Trap};
+
+// what a cell holds; how much of it is used is `Memory::modulus`, so the width is picked at run
+// time: 8 bits unless `--cell-bits` or `--cell-modulus` says otherwise
+pub type Cell = u64;
+
+pub const CELL_SIZE_LIMIT: Cell = 255; // the largest cell value, unless `--cell-modulus` changes it
+pub const ARRAY_SIZE_LIMIT: usize = 30000; // the length of a tape that doesn't grow, unless `--tape-size` changes it
+pub const INITIAL_TAPE_SIZE: usize = 1024; // the cells a growing tape starts with
+pub const FRAGMENT_CACHE_SIZE: usize = 1 << 20; // bytes of parsed fragments a machine keeps
+pub const GROWN_TAPE_LIMIT: usize = 1 << 24; // the cells a tape can grow to before the pointer is stopped
+
+// what `+` and `-` do to a cell already at the end of its range
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    #[default]
+    Wrap, // round to the other end
+    Saturate, // stay where it is
+    Trap,     // stop the run with an error
+}
+
+// what `<` and `>` do at an end of the tape
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Edge {
+    Wrap,  // go round to the other end
+    Error, // stop the run with an error
+    // make the tape longer past the right end, up to `GROWN_TAPE_LIMIT` cells; the left end
+    // wraps round to the right one as it stands, as it did when the tape was a fixed size,
+    // and the tape goes on growing from there
+    #[default]
+    Grow,
+    GrowBoth, // make it longer past either end, so cells below the origin have negative numbers
+}
+
+impl Edge {
+    // the cells a tape starts with when it isn't given a size: one that grows starts small,
+    // so a program that only uses a few cells doesn't pay for the rest
+    pub fn default_size(self) -> usize {
+        match self {
+            Edge::Grow | Edge::GrowBoth => INITIAL_TAPE_SIZE,
+            _ => ARRAY_SIZE_LIMIT,
+        }
+    }
+}
+
+// the internal memory
+#[derive(Clone)]
+pub struct Memory {
+    pub bytearray: Vec<Cell>,
+    pub idx: usize,
+    pub origin: usize,      // the index shown as cell 0
+    pub modulus: Cell,      // cells hold 0 up to one less than this; 0 means the full 64 bits
+    pub signed: bool, // shown as two's complement, so one below zero is -1 rather than the top
+    pub overflow: Overflow, // where the range ends is up to `signed`
+    pub edge: Edge,
+    pub grid: Option<Grid>, // the cells laid out in rows, which `edge` doesn't apply to
+    // the lowest and highest indexes the pointer has been at since `start_at`
+    lowest: usize,
+    highest: usize,
+}
+
+impl Default for Memory {
+    fn default() -> Memory {
+        Memory::new()
+    }
+}
+
+impl Memory {
+    // create a new array
+    pub fn new() -> Memory {
+        Memory {
+            bytearray: vec![0; INITIAL_TAPE_SIZE],
+            idx: 0,
+            origin: 0,
+            modulus: CELL_SIZE_LIMIT + 1,
+            signed: false,
+            overflow: Overflow::Wrap,
+            edge: Edge::Grow,
+            grid: None,
+            lowest: 0,
+            highest: 0,
+        }
+    }
+
+    // put the pointer on `idx` and start counting the cells it reaches from there
+    pub fn start_at(&mut self, idx: usize) {
+        self.idx = idx;
+        self.lowest = idx;
+        self.highest = idx;
+    }
+
+    // the tape index of `cell` (counting from the origin), if it's on the tape
+    fn index_of(&self, cell: isize) -> Option<usize> {
+        let index = usize::try_from(self.origin as isize + cell).ok()?;
+        (index < self.bytearray.len()).then_some(index)
+    }
+
+    // store `value` in `cell`, wrapped to the cell size; false if the cell isn't on the tape
+    pub fn set_cell(&mut self, cell: isize, value: Cell) -> bool {
+        match self.index_of(cell) {
+            Some(index) => {
+                self.bytearray[index] = value.checked_rem(self.modulus).unwrap_or(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // put the pointer on `cell`, counting it as reached; false if it isn't on the tape
+    pub fn set_pointer(&mut self, cell: isize) -> bool {
+        match self.index_of(cell) {
+            Some(index) => {
+                self.idx = index;
+                self.lowest = self.lowest.min(index);
+                self.highest = self.highest.max(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // make the tape `len` cells long, keeping what's on it; it's always at least one cell
+    pub fn resize(&mut self, len: usize) {
+        let len = len.max(1);
+        self.bytearray.resize(len, 0);
+        self.idx = self.idx.min(len - 1);
+    }
+
+    // add `cells` zeroed cells before the left end of the tape, numbered below the ones there;
+    // every cell keeps its number, so only indexes into the tape change
+    pub fn grow_left(&mut self, cells: usize) {
+        self.bytearray.splice(0..0, iter::repeat_n(0, cells));
+        self.idx += cells;
+        self.origin += cells;
+        self.lowest += cells;
+        self.highest += cells;
+    }
+
+    // keep the index within range
+    fn keep_range(&mut self) {
+        if self.idx >= self.bytearray.len() {
+            self.idx = 0;
+            self.lowest = 0;
+        }
+    }
+
+    // move the array pointer left one byte, wrapping around unless `edge` says otherwise;
+    // false if it's at the left end and can't go past it
+    fn move_left(&mut self) -> bool {
+        if self.idx == 0 && self.edge == Edge::GrowBoth {
+            // doubling, as on the right
+            let len = self.bytearray.len();
+            if len >= GROWN_TAPE_LIMIT {
+                return false;
+            }
+            self.grow_left(len.min(GROWN_TAPE_LIMIT - len));
+        }
+        if self.idx == 0 {
+            if !matches!(self.edge, Edge::Wrap | Edge::Grow) {
+                return false;
+            }
+            self.idx = self.bytearray.len() - 1;
+            self.highest = self.idx;
+        } else {
+            self.idx -= 1;
+            self.lowest = self.lowest.min(self.idx);
+        }
+        self.keep_range();
+        true
+    }
+
+    // move the array pointer right one byte, wrapping around or growing the tape as `edge`
+    // says; false if it's at the right end and can't go past it
+    fn move_right(&mut self) -> bool {
+        if self.idx + 1 >= self.bytearray.len() {
+            match self.edge {
+                Edge::Wrap => {}
+                Edge::Error => return false,
+                Edge::Grow | Edge::GrowBoth if self.bytearray.len() >= GROWN_TAPE_LIMIT => {
+                    return false
+                }
+                // doubling, so a program walking off the end a cell at a time doesn't copy
+                // the tape at every step
+                Edge::Grow | Edge::GrowBoth => {
+                    self.resize((self.bytearray.len() * 2).min(GROWN_TAPE_LIMIT))
+                }
+            }
+        }
+        self.idx += 1;
+        self.highest = self.highest.max(self.idx);
+        self.keep_range();
+        true
+    }
+
+    // move the pointer `by` cells in one go; false, leaving it where it is, if that would take
+    // it off an end of the tape, which is for `edge` to settle a cell at a time
+    fn shift(&mut self, by: isize) -> bool {
+        let target = self.idx as isize + by;
+        if !(0..self.bytearray.len() as isize).contains(&target) {
+            return false;
+        }
+        self.idx = target as usize;
+        self.lowest = self.lowest.min(self.idx);
+        self.highest = self.highest.max(self.idx);
+        true
+    }
+
+    // how many moves of `by` cells at a time it takes to reach a zero cell, as `[>]` and the
+    // like do; none if that would take the pointer off the tape
+    fn scan_length(&self, by: isize) -> Option<usize> {
+        let stride = by.unsigned_abs();
+        match by > 0 {
+            true => self.bytearray[self.idx..]
+                .iter()
+                .step_by(stride)
+                .position(|&v| v == 0),
+            false => self.bytearray[..=self.idx]
+                .iter()
+                .rev()
+                .step_by(stride)
+                .position(|&v| v == 0),
+        }
+    }
+
+    // accept one character of input
+    fn accept_in(&mut self, chr: u8) {
+        self.set_value(chr as Cell);
+    }
+
+    // store a value at the pointer, wrapped to the cell size
+    fn set_value(&mut self, value: Cell) {
+        self.bytearray[self.idx] = value.checked_rem(self.modulus).unwrap_or(value);
+    }
+
+    // provide the value at the array pointer
+    fn give_out(&mut self) -> Cell {
+        self.bytearray[self.idx]
+    }
+
+    // increment the value at pointer; false if that overflows and the policy is to trap
+    fn increment(&mut self) -> bool {
+        if !self.stays_in_range(0, 1, 0) {
+            return self.overflow == Overflow::Saturate;
+        }
+        if self.bytearray[self.idx] >= self.modulus.wrapping_sub(1) {
+            self.bytearray[self.idx] = 0;
+        } else {
+            self.bytearray[self.idx] += 1;
+        }
+        true
+    }
+
+    // decrement the value at pointer; false if that overflows and the policy is to trap
+    fn decrement(&mut self) -> bool {
+        if !self.stays_in_range(0, -1, 0) {
+            return self.overflow == Overflow::Saturate;
+        }
+        if self.bytearray[self.idx] == 0 {
+            self.bytearray[self.idx] = self.modulus.wrapping_sub(1);
+        } else {
+            self.bytearray[self.idx] -= 1;
+        }
+        true
+    }
+
+    // change the value at pointer by `delta`, wrapping as `increment` and `decrement` do
+    fn add(&mut self, delta: i64) {
+        let cell = &mut self.bytearray[self.idx];
+        *cell = match self.modulus {
+            0 => cell.wrapping_add(delta as Cell),
+            modulus if modulus <= 1 << 32 => {
+                (*cell as i64 + delta).rem_euclid(modulus as i64) as Cell
+            }
+            // too wide to add in an i64 without overflowing
+            modulus => (*cell as i128 + delta as i128).rem_euclid(modulus as i128) as Cell,
+        };
+    }
+
+    // whether the cells from `left` to `right` of the pointer are all on the tape
+    fn reaches(&self, (left, right): (isize, isize)) -> bool {
+        let idx = self.idx as isize;
+        idx + left >= 0 && idx + right < self.bytearray.len() as isize
+    }
+
+    // count the cells from `left` to `right` of the pointer as reached, as if it had been
+    // there and back; they have to be on the tape
+    fn visit(&mut self, (left, right): (isize, isize)) {
+        self.lowest = self.lowest.min((self.idx as isize + left) as usize);
+        self.highest = self.highest.max((self.idx as isize + right) as usize);
+    }
+
+    // add `factor` `times` over to the cell `offset` from the pointer, which has to be on the
+    // tape, wrapping as `add` does
+    fn add_times(&mut self, offset: isize, factor: i64, times: Cell) {
+        let cell = &mut self.bytearray[(self.idx as isize + offset) as usize];
+        *cell = match self.modulus {
+            0 => cell.wrapping_add((factor as Cell).wrapping_mul(times)),
+            modulus => {
+                let delta = (factor as i128 * times as i128).rem_euclid(modulus as i128);
+                ((*cell as i128 + delta) % modulus as i128) as Cell
+            }
+        };
+    }
+
+    // how many times round a loop counting the current cell to zero by `step` goes
+    fn times_round(&self, step: i64) -> Cell {
+        let value = self.bytearray[self.idx];
+        match (value, step < 0) {
+            (0, _) => 0,
+            (_, true) => value,
+            (_, false) => self.modulus.wrapping_sub(value),
+        }
+    }
+
+    // the smallest and largest numbers a cell holds
+    pub fn bounds(&self) -> (i128, i128) {
+        let wrap = match self.modulus {
+            0 => 1 << 64,
+            modulus => modulus as i128,
+        };
+        match self.signed {
+            true => (-(wrap / 2), (wrap - 1) / 2),
+            false => (0, wrap - 1),
+        }
+    }
+
+    // whether the cell `offset` from the pointer can change by `total`, going up to `slack`
+    // further either way on the way there, without passing an end of its range; always so
+    // when cells wrap, since then there's no end to pass. fused instructions check this
+    // before doing in one go what could overflow partway
+    fn stays_in_range(&self, offset: isize, total: i128, slack: i128) -> bool {
+        if self.overflow == Overflow::Wrap {
+            return true;
+        }
+        let value = self.number(self.bytearray[(self.idx as isize + offset) as usize]);
+        let (lowest, highest) = self.bounds();
+        value + total.min(0) - slack >= lowest && value + total.max(0) + slack <= highest
+    }
+
+    // the number a cell holding `value` stands for: with `signed`, the top half of the values
+    // are the negative ones, as in two's complement
+    pub fn number(&self, value: Cell) -> i128 {
+        let wrap = match self.modulus {
+            0 => 1 << 64,
+            modulus => modulus as i128,
+        };
+        let value = value as i128;
+        match self.signed && value >= (wrap + 1) / 2 {
+            true => value - wrap,
+            false => value,
+        }
+    }
+
+    // what a cell holds for `number`, wrapped to the cell size; negative numbers count down
+    // from the top whether or not the cells are `signed`
+    pub fn cell_for(&self, number: i128) -> Cell {
+        match self.modulus {
+            0 => number as Cell,
+            modulus => number.rem_euclid(modulus as i128) as Cell,
+        }
+    }
+
+    // get the current value at pointer
+    pub fn get_value(&self) -> Cell {
+        self.bytearray[self.idx]
+    }
+
+    // the cells from the lowest the pointer has reached to the highest
+    pub fn reached(&self) -> &[Cell] {
+        let highest = self.highest.min(self.bytearray.len() - 1);
+        &self.bytearray[self.lowest.min(highest)..=highest]
+    }
+
+    // the cells numbered `range` (counting from the origin), cut short at the ends of the tape;
+    // also gives the number of the first cell actually returned
+    pub fn window(&self, range: Range<isize>) -> (isize, &[Cell]) {
+        let lowest = -(self.origin as isize);
+        let highest = (self.bytearray.len() - self.origin) as isize;
+        let start = range.start.clamp(lowest, highest);
+        let end = range.end.clamp(start, highest);
+        let index = |cell: isize| (cell - lowest) as usize;
+        (start, &self.bytearray[index(start)..index(end)])
+    }
+}
+
+// list of all operations available to perform (including comment, which is ignored)
+#[derive(Debug, Clone, Copy)]
+pub enum Operations {
+    Add,
+    Subtract,
+    MoveLeft,
+    MoveRight,
+    Input,
+    Output,
+    BracketLeft,
+    BracketRight,
+    Host(char),          // an extension op bound to a host function
+    OutputDecimal(char), // an op mapped from an opmap to print the cell as a number
+    Clear(char),         // an op mapped from an opmap to zero the cell
+    Up(char),            // an op bound by a grid to move the pointer up a row
+    Down(char),          // and down one
+    Comment(char),
+}
+
+impl Operations {
+    pub fn from_char(c: char) -> Operations {
+        match c {
+            '+' => Operations::Add,
+            '-' => Operations::Subtract,
+            '>' => Operations::MoveRight,
+            '<' => Operations::MoveLeft,
+            '.' => Operations::Output,
+            ',' => Operations::Input,
+            '[' => Operations::BracketLeft,
+            ']' => Operations::BracketRight,
+            _ => Operations::Comment(c),
+        }
+    }
+
+    // the brainfuck character for this operation, or none for comments
+    pub fn command(&self) -> Option<char> {
+        match self {
+            Operations::Add => Some('+'),
+            Operations::Subtract => Some('-'),
+            Operations::MoveLeft => Some('<'),
+            Operations::MoveRight => Some('>'),
+            Operations::Input => Some(','),
+            Operations::Output => Some('.'),
+            Operations::BracketLeft => Some('['),
+            Operations::BracketRight => Some(']'),
+            Operations::Host(c)
+            | Operations::OutputDecimal(c)
+            | Operations::Clear(c)
+            | Operations::Up(c)
+            | Operations::Down(c) => Some(*c),
+            Operations::Comment(_) => None,
+        }
+    }
+}
+
+// how `.` turns a cell value into output text
+#[derive(Debug, Default, Clone, Copy)]
+pub enum OutputMode {
+    // the low byte of the cell, as the latin-1 character of that number
+    #[default]
+    Latin1,
+    // the whole cell as a unicode scalar value, which needs a `--cell-modulus` over 256 to go
+    // past U+00FF; values that aren't scalars come out as U+FFFD
+    Unicode,
+    // the low byte of the cell written as it is, for programs that produce binary data
+    Bytes,
+}
+
+// why a run stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    End,          // the program ran to its end
+    OutputClosed, // whatever was reading the output went away
+    Trapped,      // an error stopped it; see `trap`
+    // it ran to its end, but left a nonzero value in the cell it was asked to clear
+    Failed { cell: isize, value: Cell },
+}
+
+// the inner state of the turing machine executing the program
+pub struct Interpreter {
+    pub operations: Rc<Vec<Operations>>, // shared with the program it came from until changed
+    idx: usize,
+    pub memory: Memory,
+    pub input: Box<dyn Read>,
+    pub steps: usize,
+    pub timeline: Option<Timeline>,
+    pub profile: Option<Vec<u64>>, // times each instruction has run
+    pub output: Box<dyn Write>,
+    pub output_mode: OutputMode,
+    host_functions: HashMap<char, HostFunction>,
+    extension_events: Option<EventFn>, // told about extension side effects before they happen
+    output_len: usize,                 // bytes written by the program
+    input_len: usize,                  // bytes read by `,`
+    last_input: u8,                    // the byte `,` read last
+    needs_input: bool,                 // `,` found no input ready, and is waiting to run again
+    heartbeat: Option<HeartbeatHook>,
+    ran_last: usize,        // the instruction that ran most recently, for heartbeats
+    jumps: Rc<Vec<usize>>,  // each bracket's partner, shared like the operations
+    spans: Rc<Vec<Span>>,   // where each operation came from, if known
+    labels: Rc<Vec<Label>>, // the `=== label: name ===` comments in the source
+    pub trap: Option<Trap>, // the error that stopped the run, if any
+    pub halt: Option<Halt>, // why the run stopped, once it has
+    pub source: Rc<[u8]>,   // the text the spans point into
+    pub loop_trace: Option<LoopTrace>,
+    pub taint: Option<Taint>, // which cells and output bytes depend on input
+    depth: usize,             // loops entered and not yet left
+    pub max_depth: Option<usize>, // trap when `depth` would go past this
+    pub max_steps: Option<usize>, // trap rather than take a step past this many
+    pub time_limit: Option<Duration>, // or once the run has taken this long
+    clock: Option<(Instant, usize)>, // when it started, and the step to look at the time next
+    focus: Option<String>,    // the label tracing is limited to, if it is
+    pub watch_cell: Option<isize>, // the cell whose writes get logged, counting from the origin
+    pub history: Option<History>, // the steps taken, for winding back
+    observer: Option<Box<dyn Observer>>,
+    compiled: Option<Compiled>,
+    fragments: ProgramStore, // fragments run before, parsed, for `run_fragment`
+}
+
+impl Interpreter {
+    // a machine for `program`, reading `input` with `,` and writing to standard output
+    pub fn new(program: &str, input: &str) -> Interpreter {
+        let operations: Vec<Operations> = program.chars().map(Operations::from_char).collect();
+        let jumps = Rc::new(jump_table(&operations));
+        Interpreter::with_operations(Rc::new(operations), jumps, input.to_string())
+    }
+
+    // `new`, but with the brackets checked first: an unmatched one is an error giving its line
+    // and column, rather than something found partway through running
+    pub fn parse(program: &str, input: &str) -> Result<Interpreter, Error> {
+        let parsed = Program::parse(program.as_bytes())?;
+        Ok(Interpreter::from_program(&parsed, input.to_string()))
+    }
+
+    // a machine for an already parsed program; the program isn't copied, so one can be run
+    // any number of times with different inputs and settings
+    pub fn from_program(program: &Program, input_str: String) -> Interpreter {
+        let mut state = Interpreter::with_operations(
+            Rc::clone(&program.operations),
+            Rc::clone(&program.jumps),
+            input_str,
+        );
+        state.spans = Rc::clone(&program.spans);
+        state.labels = Rc::clone(&program.labels);
+        state.source = Rc::clone(&program.source);
+        state
+    }
+
+    // run a new machine for the same program through `run`, starting on `memory` and reading
+    // `input`, to go over it again from the beginning and look at it more closely; what it
+    // writes goes nowhere. it borrows this machine's host functions and extension event
+    // callback, so extension ops do the same as before, and hands them back after
+    pub fn rerun<R>(
+        &mut self,
+        memory: Memory,
+        input: Vec<u8>,
+        run: impl FnOnce(&mut Interpreter) -> R,
+    ) -> R {
+        let mut state = Interpreter::with_operations(
+            Rc::clone(&self.operations),
+            Rc::clone(&self.jumps),
+            String::new(),
+        );
+        state.input = Box::new(io::Cursor::new(input));
+        state.output = Box::new(io::sink());
+        state.output_mode = self.output_mode;
+        state.spans = Rc::clone(&self.spans);
+        state.labels = Rc::clone(&self.labels);
+        state.source = Rc::clone(&self.source);
+        state.memory = memory;
+        state.host_functions = mem::take(&mut self.host_functions);
+        state.extension_events = self.extension_events.take();
+        let result = run(&mut state);
+        self.host_functions = state.host_functions;
+        self.extension_events = state.extension_events;
+        result
+    }
+
+    fn with_operations(
+        operations: Rc<Vec<Operations>>,
+        jumps: Rc<Vec<usize>>,
+        input_str: String,
+    ) -> Interpreter {
+        Interpreter {
+            idx: 0,
+            memory: Memory::new(),
+            input: Box::new(io::Cursor::new(input_str.into_bytes())),
+            steps: 0,
+            timeline: None,
+            profile: None,
+            output: Box::new(io::stdout()),
+            output_mode: OutputMode::default(),
+            host_functions: HashMap::new(),
+            extension_events: None,
+            output_len: 0,
+            input_len: 0,
+            last_input: 0,
+            needs_input: false,
+            heartbeat: None,
+            ran_last: 0,
+            jumps,
+            operations,
+            spans: Rc::new(Vec::new()),
+            labels: Rc::new(Vec::new()),
+            trap: None,
+            halt: None,
+            source: Rc::from(&b""[..]),
+            loop_trace: None,
+            taint: None,
+            depth: 0,
+            max_depth: None,
+            max_steps: None,
+            time_limit: None,
+            clock: None,
+            focus: None,
+            watch_cell: None,
+            history: None,
+            observer: None,
+            compiled: None,
+            fragments: ProgramStore::new(FRAGMENT_CACHE_SIZE),
+        }
+    }
+
+    // note what the current instruction did to the tape, for the timeline, `--watch-cell` and
+    // `--taint`
+    fn record(&mut self, access: Access) {
+        let cell = self.pointer();
+        let focused = self.in_focus(self.idx);
+        if let (Some(timeline), true) = (&mut self.timeline, focused) {
+            timeline.record(self.steps, cell, access);
+        }
+        if let Some(taint) = &mut self.taint {
+            let taken = self.memory.get_value() != 0;
+            match (access, self.operations[self.idx]) {
+                (Access::Input, _) => taint.input(cell),
+                (Access::Write, Operations::Clear(_)) => taint.overwrite(cell),
+                (Access::Write, _) => taint.update(cell),
+                (Access::Output, _) => taint.output(cell, self.output_len),
+                (Access::Read, Operations::BracketLeft) if taken => taint.enter(cell),
+                (Access::Read, Operations::BracketRight) if taken => taint.again(cell),
+                (Access::Read, Operations::BracketRight) => taint.leave(),
+                _ => {}
+            }
+        }
+        if focused
+            && self.watch_cell == Some(cell)
+            && matches!(access, Access::Write | Access::Input)
+        {
+            let command = self.operations[self.idx].command().unwrap_or(' ');
+            eprintln!(
+                "cell {} = {} at step {}, {} (`{}`)",
+                cell,
+                self.memory.number(self.memory.get_value()),
+                self.steps,
+                self.place(self.idx),
+                command
+            );
+        }
+    }
+
+    // run the program until the end
+    pub fn run(&mut self) {
+        // a run to the end can't wait for input to turn up
+        if self.resume() == RunStatus::NeedsInput {
+            self.needs_input = false;
+            self.trap("unable to read input: none is ready".to_string());
+            self.beat(true);
+        }
+    }
+
+    // `run`, with a trap given back as an error instead of left in `trap`
+    pub fn try_run(&mut self) -> Result<(), Error> {
+        self.run();
+        match self.trap.take() {
+            Some(trap) => Err(Error::runtime(trap, &self.source)),
+            None => Ok(()),
+        }
+    }
+
+    // whether anything needs to see the run one instruction at a time, which rules out the
+    // fused instructions plain runs use
+    pub fn watched(&self) -> bool {
+        self.timeline.is_some()
+            || self.profile.is_some()
+            || self.loop_trace.is_some()
+            || self.taint.is_some()
+            || self.max_depth.is_some()
+            || self.watch_cell.is_some()
+            || self.history.is_some()
+            || self.observer.is_some()
+            || self.memory.grid.is_some() // the fused moves assume a straight tape
+    }
+
+    // the cells, from the origin onwards, as they stand
+    pub fn tape(&self) -> &[Cell] {
+        &self.memory.bytearray[self.memory.origin..]
+    }
+
+    // the cell the pointer is on, counting from the origin
+    pub fn pointer(&self) -> isize {
+        self.memory.idx as isize - self.memory.origin as isize
+    }
+
+    // send what `.` writes somewhere other than standard output
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.output = Box::new(output);
+    }
+
+    // take what `,` reads from `input` rather than the string the machine was made with, e.g.
+    // a file, a socket or a `Feed`
+    pub fn set_input(&mut self, input: impl Read + 'static) {
+        self.input = Box::new(input);
+    }
+
+    // bytes written to the output so far
+    pub fn output_len(&self) -> usize {
+        self.output_len
+    }
+
+    // write program output, stopping the run if it can't be written
+    fn emit(&mut self, bytes: &[u8]) -> bool {
+        match self.output.write_all(bytes) {
+            Ok(()) => {
+                self.output_len += bytes.len();
+                if let Some(observer) = &mut self.observer {
+                    bytes.iter().for_each(|&byte| observer.on_output(byte));
+                }
+                true
+            }
+            Err(e) => {
+                self.output_failed(e);
+                false
+            }
+        }
+    }
+
+    // a reader that went away (`| head`) ends the run quietly; anything else is a trap
+    fn output_failed(&mut self, error: io::Error) {
+        match error.kind() {
+            io::ErrorKind::BrokenPipe => {
+                self.halt = Some(Halt::OutputClosed);
+                self.idx = self.operations.len();
+            }
+            _ => self.trap(format!("unable to write output: {}", error)),
+        }
+    }
+
+    // the instruction that runs next
+    pub fn next_index(&self) -> usize {
+        self.idx
+    }
+
+    // where instruction `index` is in the source, for messages, with the label it's under
+    pub fn place(&self, index: usize) -> String {
+        let place = match self.spans.get(index) {
+            Some(span) => {
+                let (line, column) = program::line_col(&self.source, span.start);
+                format!("line {}, column {}", line, column)
+            }
+            None => format!("instruction {}", index),
+        };
+        match self.label_at(index) {
+            Some(label) => format!("{} in {}", place, label),
+            None => place,
+        }
+    }
+
+    // the label instruction `index` comes under, if any
+    pub fn label_at(&self, index: usize) -> Option<&str> {
+        let after = self.labels.partition_point(|label| label.index <= index);
+        after.checked_sub(1).map(|at| self.labels[at].name.as_str())
+    }
+
+    // the first instruction under the label `name`
+    pub fn label_index(&self, name: &str) -> Option<usize> {
+        self.labels
+            .iter()
+            .find(|label| label.name == name)
+            .map(|label| label.index)
+    }
+
+    // whether the source has any labels
+    pub fn has_labels(&self) -> bool {
+        !self.labels.is_empty()
+    }
+
+    // only trace the instructions under the label `name` (everywhere it appears) from now on,
+    // leaving the rest of the program to run unseen; false if there's no such label
+    pub fn focus_on_label(&mut self, name: &str) -> bool {
+        let found = self.label_index(name).is_some();
+        if found {
+            self.focus = Some(name.to_string());
+        }
+        found
+    }
+
+    // whether instruction `index` is one to trace
+    pub fn in_focus(&self, index: usize) -> bool {
+        self.focus
+            .as_deref()
+            .is_none_or(|name| self.label_at(index) == Some(name))
+    }
+
+    // the instruction starting at 1-based `line` and `column` of the source, if there is one
+    pub fn index_at(&self, line: usize, column: usize) -> Option<usize> {
+        let offset = program::offset_of(&self.source, line, column)?;
+        self.spans
+            .binary_search_by_key(&offset, |span| span.start)
+            .ok()
+    }
+
+    // stop the run with an error at the current instruction
+    fn trap(&mut self, message: String) {
+        let index = self.idx.min(self.operations.len().saturating_sub(1));
+        let loops = program::enclosing_loops(&self.operations, index)
+            .into_iter()
+            .rev()
+            .map(|(start, end)| LoopRef {
+                start,
+                end,
+                spans: self
+                    .spans
+                    .get(start)
+                    .copied()
+                    .zip(self.spans.get(end).copied()),
+            })
+            .collect();
+        self.trap = Some(Trap {
+            message,
+            index,
+            span: self.spans.get(index).copied(),
+            loops,
+        });
+        self.halt = Some(Halt::Trapped);
+        self.idx = self.operations.len();
+    }
+
+    // take away the trap that stopped the run, putting the machine back on the instruction it
+    // stopped at, so that after a limit is raised it can go on from there
+    pub fn clear_trap(&mut self) -> Option<Trap> {
+        let trap = self.trap.take()?;
+        self.idx = trap.index;
+        self.halt = None;
+        Some(trap)
+    }
+
+    // actually interpret the program
+    fn execute(&mut self) {
+        let idx2 = self.idx;
+        let oper = self.operations[idx2];
+        self.ran_last = idx2;
+        // comments aren't instructions, so they go by unseen
+        if let (Some(observer), Some(_)) = (&mut self.observer, oper.command()) {
+            observer.on_instruction(oper, idx2);
+        }
+        // println!("Running operation {:?} at location {}", oper, idx2);
+        match oper {
+            Operations::MoveLeft
+            | Operations::MoveRight
+            | Operations::Up(_)
+            | Operations::Down(_)
+                if self.memory.grid.is_some() =>
+            {
+                if !self.move_on_grid(oper) {
+                    return;
+                }
+                self.record(Access::Pointer);
+            }
+            Operations::Add => {
+                if !self.memory.increment() {
+                    let highest = self.memory.bounds().1;
+                    return self.trap(format!("overflow: the cell can't go above {}", highest));
+                }
+                self.record(Access::Write);
+            }
+            Operations::Subtract => {
+                if !self.memory.decrement() {
+                    let lowest = self.memory.bounds().0;
+                    return self.trap(format!("overflow: the cell can't go below {}", lowest));
+                }
+                self.record(Access::Write);
+            }
+            Operations::MoveLeft => {
+                if !self.memory.move_left() {
+                    return self.trap("the pointer went off the left end of the tape".to_string());
+                }
+                self.record(Access::Pointer);
+            }
+            Operations::MoveRight => {
+                if !self.memory.move_right() {
+                    let cells = self.memory.bytearray.len();
+                    return self.trap(format!(
+                        "the pointer went off the right end of the tape, after {} cells",
+                        cells
+                    ));
+                }
+                self.record(Access::Pointer);
+            }
+            Operations::Input => {
+                let mut byte = [0u8];
+                let read = match self
+                    .observer
+                    .as_mut()
+                    .and_then(|observer| observer.on_input())
+                {
+                    Some(given) => {
+                        byte[0] = given;
+                        Ok(1)
+                    }
+                    None => self.input.read(&mut byte),
+                };
+                match read {
+                    Ok(0) => self.memory.accept_in(0), // zero-terminate
+                    Ok(_) => {
+                        self.input_len += 1;
+                        self.last_input = byte[0];
+                        self.memory.accept_in(byte[0])
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        // left to run again once there's input
+                        self.needs_input = true;
+                        return;
+                    }
+                    Err(e) => return self.trap(format!("unable to read input: {}", e)),
+                }
+                self.record(Access::Input);
+            }
+            Operations::Output => {
+                let value = self.memory.give_out();
+                let mut buffer = [0; 4];
+                let bytes: &[u8] = match self.output_mode {
+                    OutputMode::Latin1 => (value as u8 as char).encode_utf8(&mut buffer).as_bytes(),
+                    OutputMode::Unicode => u32::try_from(value)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER)
+                        .encode_utf8(&mut buffer)
+                        .as_bytes(),
+                    OutputMode::Bytes => &[value as u8],
+                };
+                if !self.emit(bytes) {
+                    return;
+                }
+                self.record(Access::Output);
+            }
+            Operations::BracketLeft => {
+                self.record(Access::Read);
+                let taken = self.memory.get_value() != 0;
+                if taken {
+                    self.depth += 1;
+                    if let Some(limit) = self.max_depth.filter(|&limit| self.depth > limit) {
+                        return self.trap(format!("loops nested more than {} deep", limit));
+                    }
+                }
+                if self.loop_trace.is_some() && self.in_focus(self.idx) {
+                    let place = self.place(self.idx);
+                    if let Some(trace) = &mut self.loop_trace {
+                        trace.start(place, taken);
+                    }
+                }
+                // if zero, then directly skip the block between `[` and `]`
+                if !taken {
+                    self.idx = self.jumps[self.idx];
+                }
+            }
+            Operations::BracketRight => {
+                self.record(Access::Read);
+                // if nonzero, then jump back
+                if self.memory.get_value() != 0 {
+                    self.idx = self.jumps[self.idx];
+                    let focused = self.in_focus(self.idx);
+                    if let (Some(trace), true) = (&mut self.loop_trace, focused) {
+                        trace.again();
+                    }
+                } else {
+                    self.depth = self.depth.saturating_sub(1);
+                    let start = self.jumps[self.idx];
+                    if self.loop_trace.is_some() && self.in_focus(start) {
+                        let place = self.place(start);
+                        if let Some(trace) = &mut self.loop_trace {
+                            trace.finish(place);
+                        }
+                    }
+                }
+            }
+            Operations::Host(c) => {
+                if !self.call_host(c) {
+                    return;
+                }
+                self.record(Access::Write);
+            }
+            Operations::OutputDecimal(_) => {
+                let text = self.memory.number(self.memory.get_value()).to_string();
+                if !self.emit(text.as_bytes()) {
+                    return;
+                }
+                self.record(Access::Output);
+            }
+            Operations::Clear(_) => {
+                self.memory.set_value(0);
+                self.record(Access::Write);
+            }
+            // only a grid binds these, so off one they stay still
+            Operations::Up(_) | Operations::Down(_) => {}
+            Operations::Comment(_e) => {
+                self.idx += 1;
+                return;
+            }
+        }
+        if let Some(profile) = &mut self.profile {
+            profile[idx2] += 1;
+        }
+        // saturating, since 64-bit loops done in one go can count past what fits
+        self.steps = self.steps.saturating_add(1);
+        self.idx += 1
+    }
+}
+
+// split a source file into the program and each section of embedded input after a `!`
+pub fn split_source(contents: &str) -> (&str, Vec<&str>) {
+    split_source_exact(contents.trim())
+}
+
+// the same, but keeping the whitespace around the file, which the last input section ends with
+pub fn split_source_exact(contents: &str) -> (&str, Vec<&str>) {
+    let mut parsed = contents.split('!');
+    let program = parsed.next().unwrap_or("");
+    (program, parsed.collect())
+}
+
+// set up a machine for a source file's program and its first input section
+pub fn load_state(contents: &str) -> Interpreter {
+    let (program, inputs) = split_source(contents);
+    let input = inputs.first().copied().unwrap_or("");
+    Interpreter::new(program, input)
+}
@@ -0,0 +1,271 @@
+// `lint`: point out code that's probably a mistake. each kind of warning has a stable code, so
+// it can be allowed, warned about or denied on its own, and a comment saying `lint allow W002`
+// allows it on its own line and the next, so a messy old program can be cleaned up a bit at
+// a time. comments go by code, since the names have `-` in them, which is a command
+use std::{collections::HashMap, fs};
+
+use brainfuck_jit::{
+    program::{line_col, Program},
+    split_source, Operations,
+};
+
+pub const USAGE: &str = "lint [--allow code] [--warn code] [--deny code] [--list] [filename...]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+struct Lint {
+    code: &'static str,
+    name: &'static str,
+    description: &'static str,
+}
+
+// the codes never change meaning; new lints get new ones
+const LINTS: &[Lint] = &[
+    Lint {
+        code: "W001",
+        name: "bracket-in-prose",
+        description: "a `[` or `]` in a line of comment text, which still opens or closes a loop",
+    },
+    Lint {
+        code: "W002",
+        name: "dead-loop",
+        description: "a loop that never runs, because the cell is always zero when it's reached",
+    },
+    Lint {
+        code: "W003",
+        name: "cancelling-ops",
+        description: "commands that undo each other straight away, such as `+-` or `<>`",
+    },
+    Lint {
+        code: "W004",
+        name: "command-in-prose",
+        description: "a `+`, `-`, `<`, `>`, `,` or `.` in a line of comment text, which still runs",
+    },
+];
+
+// where each lint is in `LINTS`
+const BRACKET_IN_PROSE: usize = 0;
+const DEAD_LOOP: usize = 1;
+const CANCELLING_OPS: usize = 2;
+const COMMAND_IN_PROSE: usize = 3;
+
+// lints `word` names, by code or by name; `all` is every one
+fn lints_named(word: &str) -> Option<Vec<usize>> {
+    if word == "all" {
+        return Some((0..LINTS.len()).collect());
+    }
+    let found = LINTS
+        .iter()
+        .position(|lint| lint.code.eq_ignore_ascii_case(word) || lint.name == word)?;
+    Some(vec![found])
+}
+
+struct Finding {
+    lint: usize,
+    offset: usize, // the source byte it's about
+    message: String,
+}
+
+// how many letters make a line read as comment text rather than code with a note on it
+const PROSE_LETTERS: usize = 3;
+
+// commands on lines that are mostly words, one finding per line and lint
+fn prose_commands(source: &[u8], findings: &mut Vec<Finding>) {
+    let mut start = 0;
+    for line in source.split(|&b| b == b'\n') {
+        let letters = line.iter().filter(|b| b.is_ascii_alphabetic()).count();
+        if letters >= PROSE_LETTERS {
+            let brackets = line.iter().position(|b| b"[]".contains(b));
+            let others = line.iter().position(|b| b"+-<>,.".contains(b));
+            if let Some(at) = brackets {
+                findings.push(Finding {
+                    lint: BRACKET_IN_PROSE,
+                    offset: start + at,
+                    message: format!("`{}` in comment text is a loop bracket", line[at] as char),
+                });
+            }
+            if let Some(at) = others {
+                findings.push(Finding {
+                    lint: COMMAND_IN_PROSE,
+                    offset: start + at,
+                    message: format!("`{}` in comment text is a command", line[at] as char),
+                });
+            }
+        }
+        start += line.len() + 1;
+    }
+}
+
+// loops that start where the cell must be zero: before anything has written to the tape, or
+// straight after another loop ends
+fn dead_loops(program: &Program, findings: &mut Vec<Finding>) {
+    let (mut untouched, mut after_loop) = (true, false);
+    let mut index = 0;
+    while let Some(&operation) = program.operations.get(index) {
+        match operation {
+            Operations::BracketLeft if untouched || after_loop => {
+                findings.push(Finding {
+                    lint: DEAD_LOOP,
+                    offset: program.spans[index].start,
+                    message: match untouched {
+                        true => "this loop never runs: nothing has been written to the tape yet",
+                        false => "this loop never runs: the loop before it leaves the cell at zero",
+                    }
+                    .to_string(),
+                });
+                // what's inside it never runs either
+                index = program.jumps[index];
+                after_loop = true;
+            }
+            Operations::BracketRight => after_loop = true,
+            Operations::Comment(_) | Operations::Output => {}
+            Operations::MoveLeft | Operations::MoveRight => after_loop = false,
+            _ => (untouched, after_loop) = (false, false),
+        }
+        index += 1;
+    }
+}
+
+// a command followed by its opposite, not counting comments between them
+fn cancelling(program: &Program, findings: &mut Vec<Finding>) {
+    let commands: Vec<(usize, char)> = program
+        .operations
+        .iter()
+        .enumerate()
+        .filter_map(|(index, operation)| Some((index, operation.command()?)))
+        .filter(|(_, c)| "+-<>".contains(*c))
+        .collect();
+    let mut i = 0;
+    while i + 1 < commands.len() {
+        let ((first, a), (second, b)) = (commands[i], commands[i + 1]);
+        let adjacent = program.operations[first + 1..second]
+            .iter()
+            .all(|operation| matches!(operation, Operations::Comment(_)));
+        if adjacent && matches!((a, b), ('+', '-') | ('-', '+') | ('<', '>') | ('>', '<')) {
+            findings.push(Finding {
+                lint: CANCELLING_OPS,
+                offset: program.spans[first].start,
+                message: format!("`{}{}` does nothing", a, b),
+            });
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+}
+
+// the lints allowed by `lint allow ...` comments, by the (1-based) lines they cover
+fn suppressions(source: &[u8]) -> HashMap<usize, Vec<usize>> {
+    let mut allowed: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (number, line) in String::from_utf8_lossy(source).lines().enumerate() {
+        let Some((_, rest)) = line.split_once("lint allow") else {
+            continue;
+        };
+        let lints: Vec<usize> = rest
+            .split_whitespace()
+            .map_while(lints_named)
+            .flatten()
+            .collect();
+        for covered in [number + 1, number + 2] {
+            allowed.entry(covered).or_default().extend(&lints);
+        }
+    }
+    allowed
+}
+
+// print the findings for one file in source order; false if any were denied
+fn lint_file(path: &str, levels: &[Level]) -> bool {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Unable to read {}: {}", path, e);
+            return false;
+        }
+    };
+    let (source, _) = split_source(&contents);
+    let program = match Program::parse(source.as_bytes()) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("{}: {}", path, e);
+            return false;
+        }
+    };
+    let source = source.as_bytes();
+    let mut findings = Vec::new();
+    prose_commands(source, &mut findings);
+    dead_loops(&program, &mut findings);
+    cancelling(&program, &mut findings);
+    findings.sort_by_key(|finding| (finding.offset, finding.lint));
+
+    let allowed = suppressions(source);
+    let mut ok = true;
+    for finding in findings {
+        let (line, column) = line_col(source, finding.offset);
+        let quiet = allowed
+            .get(&line)
+            .is_some_and(|lints| lints.contains(&finding.lint));
+        let kind = match levels[finding.lint] {
+            _ if quiet => continue,
+            Level::Allow => continue,
+            Level::Warn => "warning",
+            Level::Deny => {
+                ok = false;
+                "error"
+            }
+        };
+        let lint = &LINTS[finding.lint];
+        println!(
+            "{}:{}:{}: {}[{}] {}: {}",
+            path, line, column, kind, lint.code, lint.name, finding.message
+        );
+    }
+    ok
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let usage = || {
+        println!("Usage: {} {}", name, USAGE);
+        1
+    };
+    let mut levels = vec![Level::Warn; LINTS.len()];
+    let mut files = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        let level = match arg.as_str() {
+            "--allow" => Level::Allow,
+            "--warn" => Level::Warn,
+            "--deny" => Level::Deny,
+            "--list" => {
+                for lint in LINTS {
+                    println!("{} {}: {}", lint.code, lint.name, lint.description);
+                }
+                return 0;
+            }
+            _ if arg.starts_with('-') => return usage(),
+            _ => {
+                files.push(arg);
+                continue;
+            }
+        };
+        // later flags win, so `--deny all --warn W003` denies everything else
+        match rest.next().and_then(|word| lints_named(word)) {
+            Some(lints) => lints.into_iter().for_each(|lint| levels[lint] = level),
+            None => return usage(),
+        }
+    }
+    if files.is_empty() {
+        return usage();
+    }
+    let mut status = 0;
+    for path in files {
+        if !lint_file(path, &levels) {
+            status = 1;
+        }
+    }
+    status
+}
@@ -0,0 +1,209 @@
+// a macro preprocessor for brainfuck source
+//
+//     @define name body   define a macro on one line; the body may use other macros
+//     @name               expand a macro; names are letters, digits and `_`, so `@clear->`
+//                         is `@clear` then `->`, and an `@` that isn't before a macro's name
+//                         is left as it is, a comment, like the one in an email address
+//     @use std            import the bundled standard library
+//     @use file.bf        import the definitions from another file
+use std::{collections::HashMap, fs, path::Path};
+
+pub const USAGE: &str = "expand [filename]";
+
+// the standard library, shipped inside the binary
+const STD_LIBRARY: &str = include_str!("macros/std.bf");
+
+// how deeply macros may expand into each other before we assume they recurse forever
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// the macro preprocessor's state: every definition seen so far
+struct Preprocessor<'a> {
+    macros: HashMap<String, String>,
+    base_dir: &'a Path,
+}
+
+impl Preprocessor<'_> {
+    // take definitions and imports out of `source`, returning whatever code is left
+    fn collect(&mut self, source: &str, importing: usize) -> Result<String, String> {
+        let mut code = String::new();
+        for (number, line) in source.lines().enumerate() {
+            let at_line = |e: String| format!("line {}: {}", number + 1, e);
+            let trimmed = line.trim();
+            if let Some(definition) = trimmed.strip_prefix("@define ") {
+                let definition = definition.trim_start();
+                let name: String = definition
+                    .chars()
+                    .take_while(|&c| is_name_char(c))
+                    .collect();
+                if name.is_empty() {
+                    return Err(at_line("`@define` needs a macro name".to_string()));
+                }
+                let body = definition[name.len()..].trim().to_string();
+                self.macros.insert(name, body);
+            } else if let Some(library) = trimmed.strip_prefix("@use ") {
+                if importing >= MAX_EXPANSION_DEPTH {
+                    return Err(at_line("imports are nested too deeply".to_string()));
+                }
+                let library = library.trim();
+                let source = match library {
+                    "std" => STD_LIBRARY.to_string(),
+                    path => fs::read_to_string(self.base_dir.join(path))
+                        .map_err(|e| at_line(format!("can't import `{}`: {}", path, e)))?,
+                };
+                // imports only contribute definitions, so their prose never ends up as code
+                self.collect(&source, importing + 1)
+                    .map_err(|e| format!("in `{}`, {}", library, e))?;
+            } else if importing == 0 {
+                code.push_str(line);
+                code.push('\n');
+            }
+        }
+        Ok(code)
+    }
+
+    // replace every `@name` in `code` with the macro's expanded body
+    fn expand(&self, code: &str, depth: usize) -> Result<String, String> {
+        if depth > MAX_EXPANSION_DEPTH {
+            return Err("macros expand too deeply (is one recursive?)".to_string());
+        }
+        let mut out = String::new();
+        let mut rest = code;
+        while let Some(at) = rest.find('@') {
+            out.push_str(&rest[..at]);
+            let after = &rest[at + 1..];
+            let name_len = after
+                .find(|c: char| !is_name_char(c))
+                .unwrap_or(after.len());
+            let name = &after[..name_len];
+            match self.macros.get(name) {
+                Some(body) => out.push_str(&self.expand(body, depth + 1)?),
+                None => {
+                    out.push('@');
+                    out.push_str(name);
+                }
+            }
+            rest = &after[name_len..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+// run the preprocessor over program source, resolving imports relative to `base_dir`
+pub fn preprocess(source: &str, base_dir: &Path) -> Result<String, String> {
+    let mut preprocessor = Preprocessor {
+        macros: HashMap::new(),
+        base_dir,
+    };
+    let code = preprocessor.collect(source, 0)?;
+    preprocessor.expand(&code, 0)
+}
+
+// preprocess the program part of a source file, leaving embedded input after `!` untouched
+pub fn preprocess_file(contents: &str, path: &str) -> Result<String, String> {
+    let base_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    match contents.split_once('!') {
+        Some((program, input)) => Ok(format!("{}!{}", preprocess(program, base_dir)?, input)),
+        None => preprocess(contents, base_dir),
+    }
+}
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    if args.len() != 1 {
+        println!("Usage: {} {}", name, USAGE);
+        return 1;
+    }
+    let contents = match fs::read_to_string(&args[0]) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Unable to read {}: {}", args[0], e);
+            return 1;
+        }
+    };
+    match preprocess_file(&contents, &args[0]) {
+        Ok(expanded) => {
+            print!("{}", expanded);
+            0
+        }
+        Err(e) => {
+            println!("{}: {}", args[0], e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use brainfuck_jit::{assert_bf_output, testkit::FIXTURES};
+
+    use super::preprocess;
+
+    // `program` with the standard library imported, expanded
+    fn with_std(program: &str) -> String {
+        preprocess(&format!("@use std\n{}", program), Path::new("")).unwrap()
+    }
+
+    #[test]
+    fn clear_add_to_and_copy() {
+        for &fixture in FIXTURES {
+            assert_bf_output!(fixture, &with_std("+++++@clear."), "", [0]);
+            assert_bf_output!(fixture, &with_std("+++>++<@add_to.>."), "", [0, 5]);
+            assert_bf_output!(fixture, &with_std("+++@copy.>.>."), "", [3, 3, 0]);
+        }
+    }
+
+    #[test]
+    fn if_else() {
+        // cells 2 and 3 hold `Y` and `N`, so each block prints one
+        let letters = format!(">>{}>{}<<<", "+".repeat(89), "+".repeat(78));
+        let branch = "@if >>.<< @else >>>.<<< @endif .>.";
+        for &fixture in FIXTURES {
+            let taken = with_std(&format!("{}+++{}", letters, branch));
+            assert_bf_output!(fixture, &taken, "", b"Y\0\0");
+            let not_taken = with_std(&format!("{}{}", letters, branch));
+            assert_bf_output!(fixture, &not_taken, "", b"N\0\0");
+        }
+    }
+
+    #[test]
+    fn compare() {
+        for &fixture in FIXTURES {
+            assert_bf_output!(fixture, &with_std("+++>+++<@compare.>."), "", [1, 0]);
+            assert_bf_output!(fixture, &with_std("+++>++<@compare.>."), "", [0, 0]);
+            assert_bf_output!(fixture, &with_std("@compare."), "", [1]);
+        }
+    }
+
+    #[test]
+    fn print_decimal() {
+        // x keeps its value, which is written after it; it's kept ascii so it's one byte
+        for value in [0, 7, 10, 42, 100, 127] {
+            let program = with_std(&format!("{}@print_decimal.", "+".repeat(value)));
+            let mut expected = value.to_string().into_bytes();
+            expected.push(value as u8);
+            assert_bf_output!(&program, "", expected);
+        }
+        assert_bf_output!(&with_std("-@print_decimal"), "", "255");
+    }
+
+    #[test]
+    fn names_end_at_commands() {
+        for &fixture in FIXTURES {
+            assert_bf_output!(fixture, &with_std("+++@clear->+."), "", [1]);
+            assert_bf_output!(fixture, &with_std("++@copy>-."), "", [1]);
+        }
+    }
+
+    #[test]
+    fn unknown_names_are_comments() {
+        assert_eq!(with_std("email me@example.com"), "email me@example.com\n");
+        assert_eq!(with_std("+@ @nothing+."), "+@ @nothing+.\n");
+        assert_bf_output!(&with_std("+@clearly+."), "", [2]);
+    }
+}
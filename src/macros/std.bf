@@ -0,0 +1,29 @@
+The standard macro library, imported with `@use std`
+
+Every macro works relative to the cell under the pointer (called x below),
+expects the scratch cells it mentions to be zero, leaves them zero again and
+finishes with the pointer back on x
+
+clear: set x to zero
+@define clear [-]
+
+add to: add x to the next cell and leave x at zero
+@define add_to [->+<]
+
+copy: copy x into the next cell using the one after it as scratch
+@define copy [->+>+<<]>>[-<<+>>]<<
+
+if and else: run the first block if x is nonzero and the second block
+otherwise; x is used up and the next cell is scratch; both blocks must
+finish on x and the else is required even when it is empty
+@define if >+<[
+@define else [-]>-<]>[<
+@define endif >-]<
+
+compare: set x to 1 when it equals the next cell and to 0 otherwise;
+the next cell is used up
+@define compare [->-<]+>[<->[-]]<
+
+print decimal: print x as a decimal number while keeping its value; uses
+the six cells after it as scratch
+@define print_decimal >>++++++++++<<[->+>-[>+>>]>[+[-<+>]>+>>]<<<<<<]>>[-]>>>++++++++++<[->-[>+>>]>[+[-<+>]>+>>]<<<<<]>[-]>>[>++++++[-<++++++++>]<.<<+>+>[-]]<[<[->-<]++++++[->++++++++<]>.[-]]<<++++++[-<++++++++>]<.[-]<<[-<+>]<
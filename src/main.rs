@@ -1,237 +1,888 @@
-use std::{env, fs, process::exit};
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    ops::Range,
+    path::Path,
+    process::{exit, Child, Command, Stdio},
+    time::Instant,
+};
 
-const CELL_SIZE_LIMIT: u32 = 255;
-const ARRAY_SIZE_LIMIT: usize = 30000;
+mod analysis;
+mod bench;
+mod bfasm;
+mod bracket;
+mod bundle;
+mod compile;
+mod corpus;
+mod debug;
+mod dialect;
+mod ed25519;
+mod encoder;
+mod fmt;
+mod hash;
+mod lint;
+mod macros;
+mod meta;
+mod options;
+#[cfg(all(feature = "perf", target_os = "linux"))]
+mod perf;
+mod quine_check;
+mod report;
+#[cfg(target_os = "linux")]
+mod sandbox;
 
-// the internal memory
-struct Memory {
-    bytearray: [u32; ARRAY_SIZE_LIMIT],
-    idx: usize,
-}
+#[cfg(all(feature = "plugins", unix))]
+use brainfuck_jit::plugins;
+use brainfuck_jit::{
+    grid,
+    history::History,
+    input::InputFilter,
+    program::Program,
+    progress::{Progress, Sampler},
+    split_source, split_source_exact,
+    taint::Taint,
+    timeline::Timeline,
+    trace::LoopTrace,
+    trap::Trap,
+    values::read_values,
+    Edge, Halt, Interpreter, Memory, GROWN_TAPE_LIMIT,
+};
+use options::{CellFormat, Options};
+use report::{ReportFormat, Table};
 
-impl Memory {
-    // create a new array
-    fn new() -> Memory {
-        let array = Memory {
-            bytearray: [0; ARRAY_SIZE_LIMIT],
-            idx: 0,
+// show a window of the tape on stderr, sixteen cells to a line
+fn print_cells(memory: &Memory, range: Range<isize>, format: CellFormat) {
+    let (start, window) = memory.window(range);
+    for (row, cells) in window.chunks(16).enumerate() {
+        let shown: Vec<String> = cells
+            .iter()
+            .map(|&value| match format {
+                CellFormat::Dec => format!("{:3}", memory.number(value)),
+                CellFormat::Hex => format!("{:02x}", value),
+                CellFormat::Ascii => match u32::try_from(value).ok().and_then(char::from_u32) {
+                    Some(c) if c.is_ascii_graphic() || c == ' ' => c.to_string(),
+                    _ => ".".to_string(),
+                },
+            })
+            .collect();
+        let separator = if let CellFormat::Ascii = format {
+            ""
+        } else {
+            " "
         };
-        array
+        eprintln!("{:5}: {}", start + row as isize * 16, shown.join(separator));
     }
+}
 
-    // keep the index within range
-    fn keep_range(&mut self) {
-        if self.idx >= ARRAY_SIZE_LIMIT {
-            self.idx = 0;
+// `--taint`: how many of the output bytes and of the `cells` the pointer reached depend on
+// input, and which, as runs
+fn taint_table(taint: &Taint, cells: usize) -> Table {
+    fn runs<T: std::fmt::Display>(runs: &[Range<T>]) -> String
+    where
+        Range<T>: ExactSizeIterator,
+    {
+        let runs: Vec<String> = runs
+            .iter()
+            .map(|run| match run.len() {
+                1 => run.start.to_string(),
+                _ => format!("{}..{}", run.start, run.end),
+            })
+            .collect();
+        match runs.is_empty() {
+            true => "none".to_string(),
+            false => runs.join(" "),
         }
     }
+    let (output, tape) = (taint.tainted_output(), taint.tainted_cells());
+    let tainted_output: usize = output.iter().map(|run| run.len()).sum();
+    let tainted_cells: usize = tape.iter().map(|run| run.len()).sum();
+    let mut table = Table::new(&["what", "tainted", "of", "at"]);
+    table.row(vec![
+        "output bytes".to_string(),
+        tainted_output.to_string(),
+        taint.output_len().to_string(),
+        runs(&output),
+    ]);
+    table.row(vec![
+        "cells".to_string(),
+        tainted_cells.to_string(),
+        cells.to_string(),
+        runs(&tape),
+    ]);
+    table
+}
 
-    // move the array pointer left one byte, and wraps around
-    fn move_left(&mut self) {
-        if self.idx == 0 {
-            self.idx = ARRAY_SIZE_LIMIT - 1;
-        } else {
-            self.idx -= 1;
+// how the cells the pointer reached ended up, by ranges of value: a program that tidies up
+// after itself leaves nearly all of them at zero
+fn histogram_table(memory: &Memory) -> Table {
+    const BUCKETS: [(&str, i128, i128); 7] = [
+        ("negative", i128::MIN, -1),
+        ("0", 0, 0),
+        ("1-9", 1, 9),
+        ("10-31", 10, 31),
+        ("32-126", 32, 126), // printable
+        ("127-255", 127, 255),
+        ("256+", 256, i128::MAX),
+    ];
+    let cells = memory.reached();
+    let mut counts = [0usize; BUCKETS.len()];
+    for &value in cells {
+        let number = memory.number(value);
+        if let Some(bucket) = BUCKETS
+            .iter()
+            .position(|&(_, low, high)| (low..=high).contains(&number))
+        {
+            counts[bucket] += 1;
+        }
+    }
+    let mut table = Table::new(&["values", "cells", "percent"]);
+    for (&(name, _, _), count) in BUCKETS.iter().zip(counts) {
+        table.row(vec![
+            name.to_string(),
+            count.to_string(),
+            format!("{:.1}", count as f64 * 100.0 / cells.len() as f64),
+        ]);
+    }
+    table
+}
+
+// run statistics as `metric, value` rows; the timings are left out without `secs`
+fn stats_table(steps: usize, secs: Option<f64>, counters: &[(&str, u64)]) -> Table {
+    let mut table = Table::new(&["metric", "value"]);
+    table.row(vec!["steps".to_string(), steps.to_string()]);
+    if let Some(secs) = secs {
+        table.row(vec!["time_ms".to_string(), format!("{:.3}", secs * 1000.0)]);
+        if secs > 0.0 {
+            let rate = steps as f64 / secs;
+            table.row(vec!["steps_per_sec".to_string(), format!("{:.0}", rate)]);
+        }
+    }
+    for (name, value) in counters {
+        table.row(vec![name.to_string(), value.to_string()]);
+        if *name == "instructions" && steps > 0 {
+            let per_step = *value as f64 / steps as f64;
+            table.row(vec![
+                "instructions_per_step".to_string(),
+                format!("{:.2}", per_step),
+            ]);
+        }
+    }
+    table
+}
+
+// how often each instruction ran, or was seen running, out of `total`, hottest first; plain
+// text only lists the top twenty
+fn profile_table(state: &Interpreter, counts: &[u64], total: u64, format: ReportFormat) -> Table {
+    let labels = state.has_labels();
+    let mut table = match labels {
+        true => Table::new(&["offset", "instruction", "label", "count", "percent"]),
+        false => Table::new(&["offset", "instruction", "count", "percent"]),
+    };
+    let mut hot: Vec<(usize, u64)> = counts
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|&(offset, count)| count > 0 && state.in_focus(offset))
+        .collect();
+    hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    if format == ReportFormat::Text {
+        hot.truncate(20);
+    }
+    for (offset, count) in hot {
+        let command = state.operations[offset].command().unwrap_or(' ');
+        let mut row = vec![offset.to_string(), command.to_string()];
+        if labels {
+            row.push(state.label_at(offset).unwrap_or("").to_string());
         }
-        self.keep_range()
+        row.push(count.to_string());
+        row.push(format!("{:.2}", count as f64 * 100.0 / total.max(1) as f64));
+        table.row(row);
     }
+    table
+}
 
-    // move the array pointer right one byte, wraps around
-    fn move_right(&mut self) {
-        self.idx += 1;
-        self.keep_range()
+// write a report to its file (one per batch case), or to stderr so it never mixes with output
+fn write_report(table: &Table, format: ReportFormat, path: Option<&String>, case: Option<usize>) {
+    match path {
+        Some(path) => {
+            let path = case_path(path, case);
+            or_exit(
+                fs::write(&path, table.render(format)),
+                format_args!("write {}", path),
+            )
+        }
+        None => eprint!("{}", table.render(format)),
     }
+}
 
-    // accept one character of input
-    fn accept_in(&mut self, chr: u8) {
-        self.bytearray[self.idx] = chr as u32;
+// start `command` in a shell, so the program's output feeds its stdin and its stdout
+// becomes the program's input
+fn spawn_io(command: &str, state: &mut Interpreter) -> Child {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| {
+            println!("Unable to start `{}`: {}", command, e);
+            exit(1);
+        });
+    if let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) {
+        state.output = Box::new(stdin);
+        state.input = Box::new(BufReader::new(stdout));
     }
+    child
+}
+
+// `--then-stdin`: once the embedded input runs out, `,` goes on to read `live`
+fn then_live(state: &mut Interpreter, live: Box<dyn Read>) {
+    let embedded = std::mem::replace(&mut state.input, Box::new(io::empty()));
+    state.input = Box::new(embedded.chain(live));
+}
 
-    // provide the value at the array pointer
-    fn give_out(&mut self) -> u32 {
-        self.bytearray[self.idx]
+// where a per-run file goes: `file.ext` alone, or `file-N.ext` for batch case N
+fn case_path(path: &str, case: Option<usize>) -> String {
+    match (case, path.rsplit_once('.')) {
+        (None, _) => path.to_string(),
+        (Some(case), Some((stem, extension))) => format!("{}-{}.{}", stem, case, extension),
+        (Some(case), None) => format!("{}-{}", path, case),
     }
+}
 
-    // increment the value at pointer
-    fn increment(&mut self) {
-        if self.bytearray[self.idx] >= CELL_SIZE_LIMIT {
-            self.bytearray[self.idx] = 0;
-        } else {
-            self.bytearray[self.idx] += 1;
+// set a machine up the way the options ask, before it runs
+fn configure(options: &Options, state: &mut Interpreter) {
+    state.output_mode = options.output_mode;
+    state.memory.edge = options.edge;
+    let size = options.tape_size.unwrap_or(options.edge.default_size());
+    // a growing tape starts with room for every cell the options name
+    let named = [
+        Some(0),
+        Some(options.start_cell),
+        options.watch_cell,
+        options.fail_cell,
+    ];
+    let indexes = named
+        .into_iter()
+        .flatten()
+        .map(|cell| options.origin as isize + cell);
+    let (lowest, highest) = indexes.fold((0, 0), |(lowest, highest), index| {
+        (lowest.min(index), highest.max(index))
+    });
+    let size = match options.edge {
+        Edge::Grow | Edge::GrowBoth => size.max(highest as usize + 1),
+        _ => size,
+    };
+    state.memory.resize(size);
+    state.memory.origin = options.origin;
+    if lowest < 0 {
+        state.memory.grow_left(lowest.unsigned_abs());
+    }
+    let start = state.memory.origin as isize + options.start_cell;
+    state.memory.start_at(start as usize);
+    if options.timeline_svg.is_some() {
+        state.timeline = Some(Timeline::new());
+    }
+    if options.profile {
+        state.profile = Some(vec![0; state.operations.len()]);
+    }
+    if let Some(opmap) = &options.opmap {
+        opmap.apply(state).unwrap_or_else(|e| {
+            println!("{}: {}", options.filename, e);
+            exit(1);
+        });
+    }
+    if let Some(grid) = options.grid {
+        state.use_grid(grid, grid::UP, grid::DOWN);
+    }
+    if options.progress {
+        Progress::new(options.max_steps).attach(state);
+    }
+    if options.trace_loops {
+        state.loop_trace = Some(LoopTrace::new());
+    }
+    if let Some(name) = &options.only_label {
+        if !state.focus_on_label(name) {
+            println!("There's no label `{}` in the program!", name);
+            exit(1);
+        }
+    }
+    if options.taint {
+        state.taint = Some(Taint::new());
+    }
+    state.max_depth = options.max_depth;
+    state.max_steps = options.max_steps;
+    state.time_limit = options.timeout;
+    state.watch_cell = options.watch_cell;
+    if let Some(modulus) = options.cell_modulus {
+        state.memory.modulus = modulus;
+    }
+    state.memory.signed = options.signed;
+    state.memory.overflow = options.overflow;
+    #[cfg(all(feature = "plugins", unix))]
+    for plugin in &options.loaded_plugins {
+        plugin.install(state);
+    }
+}
+
+// `--explain-opts`: what the run will do differently from the program as written
+fn explain_opts(state: &Interpreter) {
+    if state.watched() {
+        eprintln!("no optimizations: the options ask to see every instruction run");
+        return;
+    }
+    let explanations = state.explain();
+    if explanations.is_empty() {
+        eprintln!("no optimizations apply");
+    }
+    for explanation in explanations {
+        eprintln!("{}", explanation);
+    }
+}
+
+// send what `.` writes to the `--output` file (one per batch case), if there is one
+fn open_output(options: &Options, state: &mut Interpreter, case: Option<usize>) {
+    if let Some(path) = &options.output_file {
+        let path = case_path(path, case);
+        let file = or_exit(File::create(&path), format_args!("write {}", path));
+        state.set_output(BufWriter::new(file));
+    }
+}
+
+// run one program against one input, with all the reporting the options ask for; reports
+// are still written when the program traps
+fn run_case(
+    options: &Options,
+    state: &mut Interpreter,
+    case: Option<usize>,
+    newline: bool,
+    carried: Option<Memory>,
+) -> Result<Halt, Trap> {
+    configure(options, state);
+    // a tape carried over from the run before keeps its size, origin and pointer
+    if let Some(memory) = carried {
+        state.memory = memory;
+    }
+    open_output(options, state, case);
+    if options.explain_opts && case.is_none_or(|case| case == 1) {
+        explain_opts(state);
+    }
+
+    #[cfg(all(feature = "perf", target_os = "linux"))]
+    let counters = match options.perf {
+        true => match perf::PerfCounters::start() {
+            Ok(counters) => Some(counters),
+            Err(e) => {
+                eprintln!("Unable to open performance counters: {}", e);
+                None
+            }
+        },
+        false => None,
+    };
+
+    let sampler = options
+        .sample_every
+        .map(|every| Sampler::attach(state, every));
+
+    let child = options
+        .io_exec
+        .as_ref()
+        .map(|command| spawn_io(command, state));
+
+    if !options.input_options.is_identity() {
+        let input = std::mem::replace(&mut state.input, Box::new(io::empty()));
+        state.input = Box::new(InputFilter::new(input, options.input_options));
+    }
+    let recording = options.crash_dir.as_ref().map(|_| bundle::record(state));
+
+    let start = Instant::now();
+    state.run();
+    let elapsed = start.elapsed();
+
+    // closing our ends of the pipes lets the child see end of input and finish
+    if let Some(mut child) = child {
+        state.output = Box::new(io::stdout());
+        state.input = Box::new(io::empty());
+        or_exit(child.wait(), "wait for the --io-exec command");
+    }
+
+    #[cfg(all(feature = "perf", target_os = "linux"))]
+    let samples = counters.map(|c| c.stop()).unwrap_or_default();
+    #[cfg(not(all(feature = "perf", target_os = "linux")))]
+    let samples: Vec<(&str, u64)> = Vec::new();
+    let mut halt = state.halt.unwrap_or(Halt::End);
+    let asserted = match options.fail_cell {
+        Some(cell) => Some((state.memory.origin as isize + cell) as usize),
+        None => options.fail_on_nonzero.then_some(state.memory.idx),
+    };
+    if let (Halt::End, Some(idx)) = (halt, asserted) {
+        let value = state.memory.bytearray[idx];
+        if value != 0 {
+            let cell = idx as isize - state.memory.origin as isize;
+            halt = Halt::Failed { cell, value };
         }
     }
+    // the newline is only there to keep the terminal tidy, so it stays out of an output file
+    if newline
+        && !options.no_trailing_newline
+        && options.output_file.is_none()
+        && matches!(halt, Halt::End | Halt::Failed { .. })
+    {
+        let _ = writeln!(io::stdout());
+    }
 
-    // decrement the value at pointer
-    fn decrement(&mut self) {
-        if self.bytearray[self.idx] == 0 {
-            self.bytearray[self.idx] = CELL_SIZE_LIMIT;
-        } else {
-            self.bytearray[self.idx] -= 1;
+    if options.stats || options.perf {
+        let secs = (!options.deterministic).then_some(elapsed.as_secs_f64());
+        let usage = state.usage();
+        let counters: Vec<(&str, u64)> = [
+            ("tape_bytes", usage.tape_bytes as u64),
+            ("cells_touched", usage.cells_touched as u64),
+            ("input_bytes", usage.input_bytes as u64),
+            ("output_bytes", usage.output_bytes as u64),
+        ]
+        .into_iter()
+        .chain(samples)
+        .collect();
+        let table = stats_table(state.steps, secs, &counters);
+        write_report(
+            &table,
+            options.stats_format,
+            options.stats_out.as_ref(),
+            case,
+        );
+    }
+    let profile = match &sampler {
+        Some(sampler) => {
+            let counts = sampler.counts();
+            let total = counts.iter().sum();
+            Some((counts, total))
+        }
+        None => state
+            .profile
+            .clone()
+            .map(|counts| (counts, state.steps as u64)),
+    };
+    if let Some((counts, total)) = profile {
+        let table = profile_table(state, &counts, total, options.stats_format);
+        write_report(
+            &table,
+            options.stats_format,
+            options.profile_out.as_ref(),
+            case,
+        );
+    }
+    if options.value_histogram {
+        let table = histogram_table(&state.memory);
+        write_report(&table, options.stats_format, None, case);
+    }
+    if let Some(taint) = &state.taint {
+        let table = taint_table(taint, state.memory.reached().len());
+        write_report(&table, options.stats_format, None, case);
+    }
+    if let Some(range) = options.print_cells.clone() {
+        print_cells(&state.memory, range, options.cell_format);
+    }
+    if let (Some(range), Halt::End | Halt::Failed { .. }) = (options.result_cells.clone(), halt) {
+        match read_values(&state.memory, range, options.result_format) {
+            Ok(values) => {
+                let values: Vec<String> = values.iter().map(u64::to_string).collect();
+                println!("{}", values.join(" "));
+            }
+            Err(e) => eprintln!("Unable to read the result: {}", e),
         }
     }
 
-    // get the current value at pointer
-    fn get_value(&mut self) -> u32 {
-        self.bytearray[self.idx]
+    if let (Some(path), Some(timeline)) = (&options.timeline_svg, &state.timeline) {
+        let path = case_path(path, case);
+        or_exit(
+            fs::write(&path, timeline.to_svg()),
+            format_args!("write {}", path),
+        );
     }
+    if let (Some(dir), Some(recording), Some(trap)) =
+        (&options.crash_dir, &recording, state.trap.clone())
+    {
+        // in a `--sequential` batch, each case is the next file
+        let path = match (options.sequential, case) {
+            (true, Some(case)) if case > 1 => &options.more_files[case - 2],
+            _ => &options.filename,
+        };
+        bundle::write(dir, path, case, state, &trap, recording);
+    }
+    state.trap.take().map_or(Ok(halt), Err)
 }
 
-// list of all operations available to perform (including comment, which is ignored)
-#[derive(Debug)]
-enum Operations {
-    Add,
-    Subtract,
-    MoveLeft,
-    MoveRight,
-    Input,
-    Output,
-    BracketLeft,
-    BracketRight,
-    Comment(char),
+// the exit status when the output's reader went away, as if killed by SIGPIPE
+const EXIT_OUTPUT_CLOSED: i32 = 128 + 13;
+
+// stop the whole process if a run didn't finish normally
+fn finish_case(path: &str, state: &Interpreter, result: Result<Halt, Trap>) {
+    let source = &state.source;
+    match result {
+        Ok(Halt::OutputClosed) => exit(EXIT_OUTPUT_CLOSED),
+        Ok(Halt::Failed { cell, value }) => {
+            let value = state.memory.number(value);
+            eprintln!("{}: failed: cell {} is {} at the end", path, cell, value);
+            exit(1);
+        }
+        Ok(_) => {}
+        Err(trap) => {
+            eprintln!("{}: {}", path, trap.describe(source));
+            exit(1);
+        }
+    }
+}
+
+// the value, or else which thing couldn't be done and why and a failing exit, for the files
+// and processes a run can't go on without
+fn or_exit<T>(result: io::Result<T>, action: impl std::fmt::Display) -> T {
+    result.unwrap_or_else(|e| {
+        println!("Unable to {}: {}", action, e);
+        exit(1);
+    })
 }
 
-// the inner state of the turing machine executing the program
-struct InnerState {
-    operations: Vec<Operations>,
-    idx: usize,
-    memory: Memory,
-    input_str: Vec<u8>,
-    input_idx: usize,
+// read a source file, or standard input for `-`, expanding macros if asked; a program read
+// from standard input has to bring its input with it, since `,` finds standard input used up
+fn read_source(options: &Options, path: &str) -> String {
+    let contents = match path {
+        "-" => {
+            let mut contents = String::new();
+            or_exit(
+                io::stdin().read_to_string(&mut contents),
+                "read the program from standard input",
+            );
+            contents
+        }
+        _ => or_exit(fs::read_to_string(path), format_args!("read {}", path)),
+    };
+    if !options.macros {
+        return contents;
+    }
+    macros::preprocess_file(&contents, path).unwrap_or_else(|e| {
+        println!("{}: {}", path, e);
+        exit(1);
+    })
 }
 
-impl InnerState {
-    fn new(ops: Vec<char>, input_str: String) -> InnerState {
-        let operations: Vec<Operations> = ops
+fn split<'a>(options: &Options, contents: &'a str) -> (&'a str, Vec<&'a str>) {
+    match options.exact {
+        true => split_source_exact(contents),
+        false => split_source(contents),
+    }
+}
+
+fn parse_program(path: &str, source: &str) -> Program {
+    Program::parse(source.as_bytes()).unwrap_or_else(|e| {
+        println!("{}: {}", path, e);
+        exit(1);
+    })
+}
+
+// confine the process before anything untrusted runs; only the directories reports are
+// written to stay writable
+fn enter_sandbox(options: &Options) {
+    if options.io_exec.is_some() {
+        println!("`--sandbox` can't be combined with `--io-exec`!");
+        exit(1);
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let outputs = [
+            &options.timeline_svg,
+            &options.stats_out,
+            &options.profile_out,
+            &options.history_file,
+            &options.output_file,
+        ];
+        let mut dirs: Vec<&Path> = outputs
             .into_iter()
-            .map(|c| match c {
-                '+' => Operations::Add,
-                '-' => Operations::Subtract,
-                '>' => Operations::MoveRight,
-                '<' => Operations::MoveLeft,
-                '.' => Operations::Output,
-                ',' => Operations::Input,
-                '[' => Operations::BracketLeft,
-                ']' => Operations::BracketRight,
-                _ => Operations::Comment(c),
+            .flatten()
+            .map(|path| match Path::new(path).parent() {
+                Some(parent) if parent != Path::new("") => parent,
+                _ => Path::new("."),
             })
             .collect();
+        // crash bundles go in a directory of their own, which has to be there to be allowed
+        if let Some(dir) = &options.crash_dir {
+            or_exit(fs::create_dir_all(dir), format_args!("create {}", dir));
+            dirs.push(Path::new(dir));
+        }
+        if let Err(e) = sandbox::apply(&dirs) {
+            println!("Unable to enter the sandbox: {}", e);
+            exit(1);
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        println!("`--sandbox` is only supported on Linux!");
+        exit(1);
+    }
+}
 
-        let state = InnerState {
-            operations,
-            idx: 0,
-            memory: Memory::new(),
-            input_str: Vec::from(input_str.as_bytes()),
-            input_idx: 0,
-        };
-        state
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("bench") => exit(bench::main(&args[0], &args[2..])),
+        Some("stats") => exit(analysis::main(&args[0], &args[2..])),
+        Some("check") => exit(analysis::check_main(&args[0], &args[2..])),
+        Some("lint") => exit(lint::main(&args[0], &args[2..])),
+        Some("test") => exit(corpus::main(&args[0], &args[2..])),
+        Some("match") => exit(bracket::main(&args[0], &args[2..])),
+        Some("fmt") => exit(fmt::main(&args[0], &args[2..])),
+        Some("hash") => exit(hash::main(&args[0], &args[2..])),
+        Some("compile") => exit(compile::main(&args[0], &args[2..])),
+        Some("bfasm") => exit(bfasm::main(&args[0], &args[2..])),
+        Some("expand") => exit(macros::main(&args[0], &args[2..])),
+        Some("encode") => exit(encoder::text_main(&args[0], &args[2..])),
+        Some("encode-file") => exit(encoder::file_main(&args[0], &args[2..])),
+        Some("convert") => exit(dialect::main(&args[0], &args[2..])),
+        Some("quine-check") => exit(quine_check::main(&args[0], &args[2..])),
+        #[cfg(all(feature = "plugins", unix))]
+        Some("plugins") => exit(plugins::main(&args[0], &args[2..])),
+        #[cfg(not(all(feature = "plugins", unix)))]
+        Some("plugins") => {
+            println!("Plugins need a Unix build with the `plugins` feature enabled!");
+            exit(1);
+        }
+        _ => {}
+    }
+    let options = options::parse(&args);
+    let contents = match &options.eval {
+        Some(program) => program.clone(),
+        None => read_source(&options, &options.filename),
+    };
+    // every file is read before the sandbox, if any, closes the filesystem off
+    let more_sources: Vec<String> = options
+        .more_files
+        .iter()
+        .map(|path| read_source(&options, path))
+        .collect();
+    // a growing tape gets as long as it needs to
+    let tape = match options.edge {
+        Edge::Grow => 0..GROWN_TAPE_LIMIT as isize,
+        Edge::GrowBoth => -(GROWN_TAPE_LIMIT as isize)..GROWN_TAPE_LIMIT as isize,
+        _ => 0..options.tape_size.unwrap_or(options.edge.default_size()) as isize,
+    };
+    let start = options.origin as isize + options.start_cell;
+    if !tape.contains(&(options.origin as isize)) || !tape.contains(&start) {
+        println!("The start cell and origin must lie within the tape!");
+        exit(1);
+    }
+    if let Some(cell) = options.watch_cell {
+        if !tape.contains(&(options.origin as isize + cell)) {
+            println!("The watched cell must lie within the tape!");
+            exit(1);
+        }
+    }
+    if let Some(cell) = options.fail_cell {
+        if !tape.contains(&(options.origin as isize + cell)) {
+            println!("The checked cell must lie within the tape!");
+            exit(1);
+        }
+    }
+    // there's no rng or clock for a program to read, so ruling out the outside world is enough
+    if options.deterministic {
+        let refused = [
+            (options.plugins, "--plugins"),
+            (options.io_exec.is_some(), "--io-exec"),
+            (options.perf, "--perf"),
+            (options.progress, "--progress"),
+            (options.timeout.is_some(), "--timeout"),
+        ];
+        if let Some((_, flag)) = refused.iter().find(|(given, _)| *given) {
+            println!("`{}` can't be used with `--deterministic`!", flag);
+            exit(1);
+        }
+    }
+    #[cfg(not(all(feature = "perf", target_os = "linux")))]
+    if options.perf {
+        println!("`--perf` needs a Linux build with the `perf` feature enabled!");
+        exit(1);
+    }
+    #[cfg(not(all(feature = "plugins", unix)))]
+    if options.plugins {
+        println!("Plugins need a Unix build with the `plugins` feature enabled!");
+        exit(1);
+    }
+
+    let (source, mut inputs) = split(&options, &contents);
+    // an input file named outright takes the place of input embedded after `!`, unless it's
+    // to be read after it with `--then-stdin`
+    if options.input_file.is_some() && !options.then_stdin {
+        inputs.clear();
+    }
+    let program = parse_program(&options.filename, source);
+    let lines = options
+        .input_lines
+        .as_ref()
+        .map(|path| or_exit(fs::read_to_string(path), format_args!("read {}", path)));
+    // `,` reads the `--input` file when there is one, standard input otherwise
+    let input_file = options.input_file.as_ref().map(|path| {
+        File::open(path).unwrap_or_else(|e| {
+            println!("Unable to read {}: {}", path, e);
+            exit(1);
+        })
+    });
+    let live_input = || -> Box<dyn Read> {
+        match &input_file {
+            Some(file) => Box::new(or_exit(file.try_clone(), "read the input file")),
+            None => Box::new(io::stdin()),
+        }
+    };
+    if options.sandbox {
+        enter_sandbox(&options);
     }
 
-    // get the idx of the next brace
-    fn get_next_rbrack(&self) -> usize {
-        let mut idx2 = self.idx + 1;
-        let mut othercount = 0; // the count of non relavent braces
-        loop {
-            match self.operations[idx2] {
-                Operations::BracketRight => {
-                    if othercount == 0 {
-                        break;
-                    } else {
-                        othercount -= 1;
+    // with `--meta-shortcut`, a self-interpreter's input is split into the program it would
+    // run and that program's own input, and the inner program is run directly
+    let shortcut = options.meta_shortcut && meta::is_self_interpreter(source);
+    if options.meta_shortcut && !shortcut {
+        eprintln!(
+            "{}: not a recognised self-interpreter, running it as it is",
+            options.filename
+        );
+    }
+    let label = match shortcut {
+        true => format!("{} (inner program)", options.filename),
+        false => options.filename.clone(),
+    };
+    let start = |input: &str| {
+        let mut state = match shortcut {
+            false => Interpreter::from_program(&program, input.to_string()),
+            true => {
+                let (inner, rest) = meta::split_input(input);
+                let inner = parse_program(&label, inner);
+                if options.verify_meta {
+                    if let Err(e) = meta::verify(source, input) {
+                        println!("{}: {}", options.filename, e);
+                        exit(1);
                     }
                 }
-                Operations::BracketLeft => {
-                    othercount += 1;
-                }
-                _ => {}
+                Interpreter::from_program(&inner, rest.to_string())
             }
-            idx2 += 1;
-        }
-        idx2
-    }
-
-    // get location of previous lbrace
-    fn get_prev_lbrack(&self) -> usize {
-        let mut idx2 = self.idx - 1;
-        let mut othercount = 0;
-        loop {
-            match self.operations[idx2] {
-                Operations::BracketLeft => {
-                    if othercount == 0 {
-                        break;
-                    } else {
-                        othercount -= 1;
-                    }
-                }
-                Operations::BracketRight => {
-                    othercount += 1;
-                }
-                _ => {}
-            }
-            idx2 -= 1;
-        }
-        idx2
-    }
-
-    // actually interpret the program
-    fn execute(&mut self) {
-        let idx2 = self.idx;
-        let oper = &self.operations[idx2];
-        // println!("Running operation {:?} at location {}", oper, idx2);
-        match oper {
-            Operations::Add => self.memory.increment(),
-            Operations::Subtract => self.memory.decrement(),
-            Operations::MoveLeft => self.memory.move_left(),
-            Operations::MoveRight => self.memory.move_right(),
-            Operations::Input => {
-                if self.input_idx >= self.input_str.len() {
-                    self.memory.accept_in(0 as u8); // zero-terminate
-                } else {
-                    self.memory.accept_in(self.input_str[self.input_idx] as u8);
-                }
-                self.input_idx += 1;
+        };
+        if options.then_stdin {
+            then_live(&mut state, live_input());
+        }
+        state
+    };
+    let check = |state: &Interpreter, result| finish_case(&label, state, result);
+
+    // `debug` takes its commands from standard input, so the program only gets embedded input
+    // or the `--input` file
+    if options.debug {
+        let mut state = start(inputs.first().copied().unwrap_or(""));
+        if inputs.is_empty() && input_file.is_some() {
+            state.input = live_input();
+        }
+        configure(&options, &mut state);
+        open_output(&options, &mut state, None);
+        let history = match &options.history_file {
+            Some(path) => History::new(debug::HISTORY_STEPS).spill_to(
+                Path::new(path),
+                options.history_cap.unwrap_or(debug::HISTORY_CAP),
+            ),
+            None => Ok(History::new(debug::HISTORY_STEPS)),
+        };
+        match history {
+            Ok(history) => state.history = Some(history),
+            Err(e) => {
+                println!(
+                    "Unable to open {}: {}",
+                    options.history_file.unwrap_or_default(),
+                    e
+                );
+                exit(1);
             }
-            Operations::Output => print!("{}", self.memory.give_out() as u8 as char),
-            Operations::BracketLeft => {
-                // if zero, then directly skip the block between `[` and `]`
-                if self.memory.get_value() == 0 {
-                    self.idx = self.get_next_rbrack();
+        }
+        exit(debug::main(&mut state, options.cell_format));
+    }
+
+    // `--sequential` runs each file in turn on its first input, or standard input if it has
+    // none; with `--carry-tape` each starts on the tape the one before left behind
+    if options.sequential {
+        let files = options.more_files.iter().zip(&more_sources);
+        let mut tape = None;
+        for (case, (path, contents)) in [(&options.filename, &contents)]
+            .into_iter()
+            .chain(files)
+            .enumerate()
+        {
+            let (source, inputs) = split(&options, contents);
+            let program = parse_program(path, source);
+            let mut state = match inputs.first() {
+                Some(input) => {
+                    let mut state = Interpreter::from_program(&program, input.to_string());
+                    if options.then_stdin {
+                        then_live(&mut state, live_input());
+                    }
+                    state
                 }
-            }
-            Operations::BracketRight => {
-                // if nonzero, then jump back
-                if self.memory.get_value() != 0 {
-                    self.idx = self.get_prev_lbrack();
+                None => {
+                    let mut state = Interpreter::from_program(&program, String::new());
+                    state.input = live_input();
+                    state
                 }
+            };
+            let result = run_case(&options, &mut state, Some(case + 1), true, tape.take());
+            finish_case(path, &state, result);
+            if options.carry_tape {
+                tape = Some(state.memory);
             }
-            Operations::Comment(_e) => {}
         }
-        self.idx += 1
+        return;
     }
-}
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: {} [filename]", args[0]);
-        exit(1);
+    // `--input-lines` runs the program once per line, joining the outputs with a separator
+    if let Some(lines) = lines {
+        let separator = options.separator.as_deref().unwrap_or("\n");
+        for (case, line) in lines.lines().enumerate() {
+            if case > 0 {
+                print!("{}", separator);
+            }
+            let mut state = start(line);
+            let result = run_case(&options, &mut state, Some(case + 1), false, None);
+            check(&state, result);
+        }
+        if !options.no_trailing_newline {
+            let _ = writeln!(io::stdout());
+        }
+        return;
     }
-    let contents = fs::read_to_string(&args[1]).expect("Unable to read file!");
-    let mut parsed = contents.trim().split("!").collect::<Vec<&str>>();
 
-    if parsed.len() < 2 {
-        parsed.push("");
-    }
-    if parsed.len() > 2 {
-        println!("Please use only one `!` in the input file!");
-        exit(1);
+    // without embedded input, `,` reads standard input
+    if inputs.is_empty() {
+        let mut state = match shortcut {
+            true => {
+                let mut text = String::new();
+                or_exit(live_input().read_to_string(&mut text), "read the input");
+                start(&text)
+            }
+            false => {
+                let mut state = start("");
+                state.input = live_input();
+                state
+            }
+        };
+        let result = run_case(&options, &mut state, None, true, None);
+        check(&state, result);
+        return;
     }
 
-    let mut state = InnerState::new(
-        parsed[0].to_string().chars().collect(),
-        parsed[1].to_string(),
-    );
-    while state.idx < parsed[0].len() {
-        state.execute();
+    // `program!input1!input2` runs the program once per input, each output under a header
+    let batch = inputs.len() > 1;
+    for (case, input) in inputs.iter().enumerate() {
+        if batch {
+            println!("--- input {} ---", case + 1);
+        }
+        let mut state = start(input);
+        let result = run_case(&options, &mut state, batch.then_some(case + 1), true, None);
+        check(&state, result);
     }
-    println!("");
 }
@@ -0,0 +1,62 @@
+// `--meta-shortcut`: when the program is a brainfuck self-interpreter reading `program!input`,
+// run that inner program directly instead of interpreting the interpreter
+use std::io::Read;
+
+use brainfuck_jit::stream::OutputReader;
+
+// small programs in the form self-interpreters read, and what they should print
+const PROBES: &[(&str, &str)] = &[
+    (",[.,]!abc", "abc"),
+    ("++++[>++++[>++++<-]<-]>>+.!", "A"),
+    (">,>,.<.!xy", "yx"),
+];
+
+// how long a probe may run; interpreting these takes well under this
+const PROBE_STEPS: usize = 10_000_000;
+
+// what `program` prints on `input`, or why it didn't get to the end within `limit` steps
+fn output(program: &str, input: &str, limit: Option<usize>) -> Result<Vec<u8>, String> {
+    let mut reader = OutputReader::new(program, input);
+    if let Some(limit) = limit {
+        reader = reader.step_limit(limit);
+    }
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output).map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+// whether `program` behaves like a self-interpreter: it has to run every probe correctly
+pub fn is_self_interpreter(program: &str) -> bool {
+    PROBES.iter().all(|(input, expected)| {
+        output(program, input, Some(PROBE_STEPS)).as_deref() == Ok(expected.as_bytes())
+    })
+}
+
+// the inner program and its input, out of what the self-interpreter would have read
+pub fn split_input(input: &str) -> (&str, &str) {
+    input.split_once('!').unwrap_or((input, ""))
+}
+
+// run both the self-interpreter and the inner program on `input` and say where their
+// outputs part ways, if they do
+pub fn verify(interpreter: &str, input: &str) -> Result<(), String> {
+    let (inner, inner_input) = split_input(input);
+    let full = output(interpreter, input, None)
+        .map_err(|e| format!("the self-interpreter stopped: {}", e))?;
+    let shortcut = output(inner, inner_input, None)
+        .map_err(|e| format!("the inner program stopped: {}", e))?;
+    if full == shortcut {
+        return Ok(());
+    }
+    let offset = full
+        .iter()
+        .zip(&shortcut)
+        .position(|(a, b)| a != b)
+        .unwrap_or(full.len().min(shortcut.len()));
+    Err(format!(
+        "the shortcut's output differs from the interpreter's at byte {} ({} bytes against {})",
+        offset,
+        shortcut.len(),
+        full.len()
+    ))
+}
@@ -0,0 +1,24 @@
+// a hook the machine calls as it runs, so a gui or test harness can watch the program and step
+// in on its i/o without a copy of the interpreter loop
+use crate::{Interpreter, Operations};
+
+// every method does nothing by default, so an observer only writes the ones it cares about
+pub trait Observer {
+    // a byte the program wrote, after it went to the output
+    fn on_output(&mut self, _byte: u8) {}
+
+    // asked before `,` reads: a byte given here is read instead of the next one from the input
+    fn on_input(&mut self) -> Option<u8> {
+        None
+    }
+
+    // `operation`, instruction `index`, is about to run
+    fn on_instruction(&mut self, _operation: Operations, _index: usize) {}
+}
+
+impl Interpreter {
+    // tell `observer` about the run from here on; it replaces any observer set before
+    pub fn observe(&mut self, observer: impl Observer + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
+}
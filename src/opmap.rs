@@ -0,0 +1,180 @@
+// remapping single-character ops to built-in behaviours from a toml file, e.g.
+//
+//     [ops]
+//     ";" = "output-decimal"   # print the cell as a number
+//     "z" = "clear"
+//     "a" = "add"             # `a` works like `+`
+//
+// the brainfuck commands themselves can be remapped too
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    error::Error,
+    program::{jump_table, line_col},
+    Interpreter, Operations,
+};
+
+// what a mapped character does
+#[derive(Debug, Clone, Copy)]
+pub enum Behavior {
+    Add,
+    Subtract,
+    MoveLeft,
+    MoveRight,
+    Input,
+    Output,
+    LoopStart,
+    LoopEnd,
+    OutputDecimal, // write the cell as a decimal number
+    Clear,         // set the cell to zero
+    Ignore,        // treat the character as a comment
+}
+
+impl Behavior {
+    fn parse(name: &str) -> Option<Behavior> {
+        match name {
+            "add" => Some(Behavior::Add),
+            "subtract" => Some(Behavior::Subtract),
+            "left" => Some(Behavior::MoveLeft),
+            "right" => Some(Behavior::MoveRight),
+            "input" => Some(Behavior::Input),
+            "output" => Some(Behavior::Output),
+            "loop-start" => Some(Behavior::LoopStart),
+            "loop-end" => Some(Behavior::LoopEnd),
+            "output-decimal" => Some(Behavior::OutputDecimal),
+            "clear" => Some(Behavior::Clear),
+            "ignore" => Some(Behavior::Ignore),
+            _ => None,
+        }
+    }
+
+    fn operation(self, c: char) -> Operations {
+        match self {
+            Behavior::Add => Operations::Add,
+            Behavior::Subtract => Operations::Subtract,
+            Behavior::MoveLeft => Operations::MoveLeft,
+            Behavior::MoveRight => Operations::MoveRight,
+            Behavior::Input => Operations::Input,
+            Behavior::Output => Operations::Output,
+            Behavior::LoopStart => Operations::BracketLeft,
+            Behavior::LoopEnd => Operations::BracketRight,
+            Behavior::OutputDecimal => Operations::OutputDecimal(c),
+            Behavior::Clear => Operations::Clear(c),
+            Behavior::Ignore => Operations::Comment(c),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct OpMap {
+    ops: HashMap<char, Behavior>,
+}
+
+// a toml string (basic or literal) holding the rest of the line, and what follows it
+fn string(text: &str) -> Option<(String, &str)> {
+    let quote = text.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let mut value = String::new();
+    let mut chars = text[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            _ if c == quote => return Some((value, &text[i + 2..])),
+            '\\' if quote == '"' => match chars.next()?.1 {
+                '"' => value.push('"'),
+                '\\' => value.push('\\'),
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                _ => return None,
+            },
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+impl OpMap {
+    pub fn parse(text: &str) -> Result<OpMap, Error> {
+        let mut map = OpMap::default();
+        for (number, line) in text.lines().enumerate() {
+            let error = |message: &str| Error::parse_line(number + 1, message);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(table) = line.strip_prefix('[') {
+                let name = table.split(']').next().unwrap_or("").trim();
+                if name != "ops" {
+                    return Err(error(&format!("unknown table `[{}]`", name)));
+                }
+                continue;
+            }
+            let (key, rest) = match line.chars().next() {
+                Some('"' | '\'') => string(line).ok_or_else(|| error("unterminated key"))?,
+                _ => {
+                    let end = line.find([' ', '=']).unwrap_or(line.len());
+                    (line[..end].to_string(), &line[end..])
+                }
+            };
+            let rest = rest.trim_start();
+            let rest = rest
+                .strip_prefix('=')
+                .ok_or_else(|| error("expected `=`"))?
+                .trim_start();
+            let (value, rest) = string(rest).ok_or_else(|| error("expected a quoted name"))?;
+            let rest = rest.trim_start();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(error("unexpected text after the value"));
+            }
+            let mut chars = key.chars();
+            let op = match (chars.next(), chars.next()) {
+                (Some(op), None) if op != '!' => op,
+                _ => return Err(error(&format!("`{}` isn't a single op character", key))),
+            };
+            let behavior = Behavior::parse(&value)
+                .ok_or_else(|| error(&format!("unknown behaviour `{}`", value)))?;
+            map.ops.insert(op, behavior);
+        }
+        Ok(map)
+    }
+
+    // rewrite the program's ops by their source character. the loops have to still pair up
+    // afterwards, so a map that leaves a bracket without a partner is an error, at that
+    // bracket's place in the source, and the program is left as it was
+    pub fn apply(&self, state: &mut Interpreter) -> Result<(), Error> {
+        let mut operations = state.operations.to_vec();
+        for operation in operations.iter_mut() {
+            let c = match *operation {
+                Operations::Comment(c) => c,
+                _ => operation.command().unwrap_or(' '),
+            };
+            if let Some(behavior) = self.ops.get(&c) {
+                *operation = behavior.operation(c);
+            }
+        }
+        // the map may have moved loops around, so their jumps have to be found again
+        let jumps = jump_table(&operations);
+        let unmatched =
+            operations
+                .iter()
+                .enumerate()
+                .find_map(|(i, operation)| match (operation, jumps[i]) {
+                    (Operations::BracketLeft, partner) if partner == operations.len() => {
+                        Some((i, '['))
+                    }
+                    (Operations::BracketRight, partner) if partner == i => Some((i, ']')),
+                    _ => None,
+                });
+        if let Some((i, bracket)) = unmatched {
+            let message = format!("the op map leaves an unmatched `{}`", bracket);
+            return Err(match state.spans.get(i) {
+                Some(span) => {
+                    let (line, column) = line_col(&state.source, span.start);
+                    Error::parse_at(line, column, message)
+                }
+                None => Error::parse(message),
+            });
+        }
+        state.operations = Rc::new(operations);
+        state.jumps = Rc::new(jumps);
+        Ok(())
+    }
+}
@@ -0,0 +1,518 @@
+// the command line for `run` and `debug`: what each flag does to `Options`, and how it's used
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    ops::Range,
+    process::exit,
+    time::Duration,
+};
+
+#[cfg(all(feature = "plugins", unix))]
+use brainfuck_jit::plugins;
+use brainfuck_jit::{
+    grid::Grid,
+    input::{Case, InputOptions},
+    opmap::OpMap,
+    values::ValueFormat,
+    Cell, Edge, OutputMode, Overflow,
+};
+
+use crate::{
+    analysis, bench, bfasm, bracket, compile, corpus, dialect, encoder, fmt, hash, lint, macros,
+    or_exit, quine_check, report::ReportFormat,
+};
+
+// the options `run` and `debug` take
+#[derive(Default)]
+pub struct Options {
+    pub filename: String,
+    pub timeline_svg: Option<String>,
+    pub stats: bool,
+    pub perf: bool,
+    pub macros: bool,
+    pub print_cells: Option<Range<isize>>,
+    pub start_cell: isize,
+    pub origin: usize,
+    pub input_lines: Option<String>,
+    pub separator: Option<String>,
+    pub stats_format: ReportFormat,
+    pub stats_out: Option<String>,
+    pub profile: bool,
+    pub sample_every: Option<usize>, // profile by sampling instead, every so many steps
+    pub profile_out: Option<String>,
+    pub value_histogram: bool,
+    pub crash_dir: Option<String>,
+    pub output_file: Option<String>, // written by `.` instead of standard output
+    pub eval: Option<String>,        // the program itself, given on the command line
+    pub io_exec: Option<String>,
+    pub output_mode: OutputMode,
+    pub cell_format: CellFormat,
+    pub input_options: InputOptions,
+    pub plugins: bool,
+    #[cfg(all(feature = "plugins", unix))]
+    pub loaded_plugins: Vec<plugins::Plugin>,
+    pub sandbox: bool,
+    pub progress: bool,
+    pub trace_loops: bool,
+    pub only_label: Option<String>, // trace and profile only the code under this label
+    pub taint: bool,                // report what depends on input
+    pub max_depth: Option<usize>,
+    pub max_steps: Option<usize>,
+    pub timeout: Option<Duration>,
+    pub cell_modulus: Option<Cell>,
+    pub signed: bool, // show cells as two's complement numbers
+    pub overflow: Overflow,
+    pub edge: Edge,
+    pub grid: Option<Grid>, // the tape laid out in rows, with `^` and `v` moving between them
+    pub sequential: bool,
+    pub carry_tape: bool,
+    pub no_trailing_newline: bool,
+    pub exact: bool,
+    pub watch_cell: Option<isize>,
+    pub fail_on_nonzero: bool, // the run fails if the final current cell isn't zero
+    pub fail_cell: Option<isize>, // or if this cell isn't
+    pub deterministic: bool,   // refuse anything whose result could differ between runs
+    pub meta_shortcut: bool,   // run what a self-interpreter is given instead of the interpreter
+    pub verify_meta: bool,     // and check that gives the same output
+    pub tape_size: Option<usize>,
+    pub input_file: Option<String>, // read by `,` instead of standard input
+    pub debug: bool,                // step through the program instead of just running it
+    pub history_file: Option<String>, // where `debug` keeps older steps to wind back through
+    pub history_cap: Option<u64>,   // and how big that file can get
+    pub then_stdin: bool,           // read live input after the embedded input is used up
+    pub result_cells: Option<Range<isize>>, // printed as numbers once the program ends
+    pub result_format: ValueFormat,
+    pub explain_opts: bool,      // list the optimizations made before running
+    pub more_files: Vec<String>, // files after the first, run in turn with `--sequential`
+    pub opmap: Option<OpMap>,
+}
+
+// how `--print-cells` shows each cell
+#[derive(Default, Clone, Copy)]
+pub enum CellFormat {
+    #[default]
+    Dec,
+    Hex,
+    Ascii,
+}
+
+// expand `\n`, `\t` and `\\` in text given on the command line
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => out.push('\n'),
+            ('\\', Some('t')) => out.push('\t'),
+            ('\\', Some('\\')) => out.push('\\'),
+            _ => {
+                out.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    out
+}
+
+// parse a cell range: `a..b`, `a..=b` or a single cell `a`
+pub fn parse_range(text: &str) -> Option<Range<isize>> {
+    if let Some((start, end)) = text.split_once("..=") {
+        let end: isize = end.parse().ok()?;
+        Some(start.parse().ok()?..end + 1)
+    } else if let Some((start, end)) = text.split_once("..") {
+        Some(start.parse().ok()?..end.parse().ok()?)
+    } else {
+        let cell: isize = text.parse().ok()?;
+        Some(cell..cell + 1)
+    }
+}
+
+// parse a size in bytes, optionally in `K`, `M` or `G` (powers of 1024)
+fn parse_size(text: &str) -> Option<u64> {
+    let (digits, scale) = match text.char_indices().last()? {
+        (at, 'K' | 'k') => (&text[..at], 1 << 10),
+        (at, 'M' | 'm') => (&text[..at], 1 << 20),
+        (at, 'G' | 'g') => (&text[..at], 1 << 30),
+        _ => (text, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(scale)
+}
+
+// parse the command line, exiting with a usage message if it doesn't make sense
+pub fn parse(args: &[String]) -> Options {
+    let usage = || -> ! {
+        print_usage(&args[0]);
+        exit(1);
+    };
+    let mut options = Options::default();
+    let mut filenames = Vec::new();
+    let mut grid_wrap = None;
+    // `run` is optional: `bf run file` is the same as `bf file`
+    options.debug = args.get(1).is_some_and(|arg| arg == "debug");
+    let skip = if args.get(1).is_some_and(|arg| arg == "run") || options.debug {
+        2
+    } else {
+        1
+    };
+    // the flag that set the cell size and the one that set the tape's ends, if any did
+    let (mut cell_size, mut tape_ends) = (None, None);
+    let mut rest = args[skip..].iter();
+    while let Some(arg) = rest.next() {
+        let mut value = || rest.next().cloned().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--timeline-svg" => options.timeline_svg = Some(value()),
+            "--stats" => options.stats = true,
+            "--perf" => options.perf = true,
+            "--macros" => options.macros = true,
+            "--print-cells" => {
+                options.print_cells = Some(parse_range(&value()).unwrap_or_else(|| usage()))
+            }
+            "--start-cell" => options.start_cell = value().parse().unwrap_or_else(|_| usage()),
+            "--origin" => options.origin = value().parse().unwrap_or_else(|_| usage()),
+            "--input-lines" => options.input_lines = Some(value()),
+            "--separator" => options.separator = Some(unescape(&value())),
+            "--profile" => options.profile = true,
+            "--sample-every" => match value().parse() {
+                Ok(every) if every > 0 => options.sample_every = Some(every),
+                _ => usage(),
+            },
+            "--value-histogram" => options.value_histogram = true,
+            "--crash-dir" => options.crash_dir = Some(value()),
+            "--output" => options.output_file = Some(value()),
+            "-e" | "--eval" => options.eval = Some(value()),
+            "--stats-format" => {
+                options.stats_format = ReportFormat::parse(&value()).unwrap_or_else(|| usage())
+            }
+            "--stats-out" => options.stats_out = Some(value()),
+            "--profile-out" => options.profile_out = Some(value()),
+            "--io-exec" => options.io_exec = Some(value()),
+            "--output-mode" => {
+                options.output_mode = match value().as_str() {
+                    "latin1" => OutputMode::Latin1,
+                    "unicode" => OutputMode::Unicode,
+                    "bytes" => OutputMode::Bytes,
+                    _ => usage(),
+                }
+            }
+            "--strip-newline" => options.input_options.strip_newline = true,
+            "--crlf" => options.input_options.crlf = true,
+            "--hex-input" => options.input_options.hex = true,
+            "--plugins" => options.plugins = true,
+            "--sandbox" => options.sandbox = true,
+            "--progress" => options.progress = true,
+            "--trace-loops" => options.trace_loops = true,
+            "--only-label" => options.only_label = Some(value()),
+            "--taint" => options.taint = true,
+            "--history-file" => options.history_file = Some(value()),
+            "--history-cap" => match parse_size(&value()) {
+                Some(0) | None => usage(),
+                Some(cap) => options.history_cap = Some(cap),
+            },
+            "--tape-size" => match value().parse() {
+                Ok(0) | Err(_) => usage(),
+                Ok(size) => options.tape_size = Some(size),
+            },
+            // a modulus of 0 stands for 2^64, which doesn't fit in a cell
+            "--cell-bits" => {
+                claim(&mut cell_size, arg);
+                match value().parse::<u32>() {
+                    Ok(bits @ 1..=63) => options.cell_modulus = Some(1 << bits),
+                    Ok(64) => options.cell_modulus = Some(0),
+                    _ => usage(),
+                }
+            }
+            "--cell-width" => {
+                claim(&mut cell_size, arg);
+                match value().parse::<u32>() {
+                    Ok(bits @ (8 | 16 | 32)) => options.cell_modulus = Some(1 << bits),
+                    Ok(64) => options.cell_modulus = Some(0),
+                    _ => usage(),
+                }
+            }
+            "--input" | "--input-file" => options.input_file = Some(value()),
+            "--then-stdin" => options.then_stdin = true,
+            "--result-cells" => {
+                options.result_cells = Some(parse_range(&value()).unwrap_or_else(|| usage()))
+            }
+            "--result-format" => {
+                options.result_format = ValueFormat::parse(&value()).unwrap_or_else(|| usage())
+            }
+            "--cell-modulus" => {
+                claim(&mut cell_size, arg);
+                match value().parse() {
+                    Ok(0) | Err(_) => usage(),
+                    Ok(modulus) => options.cell_modulus = Some(modulus),
+                }
+            }
+            "--signed" => options.signed = true,
+            "--overflow" => {
+                options.overflow = match value().as_str() {
+                    "wrap" => Overflow::Wrap,
+                    "saturate" => Overflow::Saturate,
+                    "trap" => Overflow::Trap,
+                    _ => usage(),
+                }
+            }
+            "--tape-edge" => {
+                claim(&mut tape_ends, arg);
+                options.edge = match value().as_str() {
+                    "wrap" => Edge::Wrap,
+                    "error" => Edge::Error,
+                    "grow" => Edge::Grow,
+                    _ => usage(),
+                }
+            }
+            "--grid" => options.grid = Some(Grid::parse(&value()).unwrap_or_else(|| usage())),
+            "--grid-edge" => {
+                grid_wrap = Some(match value().as_str() {
+                    "wrap" => true,
+                    "trap" => false,
+                    _ => usage(),
+                })
+            }
+            "--tape-model" => {
+                claim(&mut tape_ends, arg);
+                options.edge = match value().as_str() {
+                    "unbounded-right" => Edge::Grow,
+                    "unbounded-both" => Edge::GrowBoth,
+                    _ => usage(),
+                }
+            }
+            "--max-depth" => options.max_depth = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--timeout" => {
+                options.timeout = Some(parse_duration(&value()).unwrap_or_else(|| usage()))
+            }
+            "--max-steps" => options.max_steps = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--opmap" => {
+                let path = value();
+                let text = or_exit(fs::read_to_string(&path), format_args!("read {}", path));
+                options.opmap = Some(OpMap::parse(&text).unwrap_or_else(|e| {
+                    println!("{}: {}", path, e);
+                    exit(1);
+                }));
+            }
+            "--input-case" => {
+                options.input_options.case = match value().as_str() {
+                    "upper" => Case::Upper,
+                    "lower" => Case::Lower,
+                    _ => usage(),
+                }
+            }
+            "--format" => {
+                options.cell_format = match value().as_str() {
+                    "dec" => CellFormat::Dec,
+                    "hex" => CellFormat::Hex,
+                    "ascii" => CellFormat::Ascii,
+                    _ => usage(),
+                }
+            }
+            "--sequential" => options.sequential = true,
+            "--carry-tape" => options.carry_tape = true,
+            "--no-trailing-newline" => options.no_trailing_newline = true,
+            "--exact" => options.exact = true,
+            "--watch-cell" => {
+                options.watch_cell = Some(value().parse().unwrap_or_else(|_| usage()))
+            }
+            "--deterministic" => options.deterministic = true,
+            "--explain-opts" => options.explain_opts = true,
+            "--meta-shortcut" => options.meta_shortcut = true,
+            "--verify-meta" => {
+                options.meta_shortcut = true;
+                options.verify_meta = true;
+            }
+            "--fail-on-nonzero" => options.fail_on_nonzero = true,
+            "--fail-cell" => options.fail_cell = Some(value().parse().unwrap_or_else(|_| usage())),
+            _ if !arg.starts_with("--") => filenames.push(arg.clone()),
+            _ => usage(),
+        }
+    }
+    // with no file named, a program piped in is read from standard input, as with `-`
+    if filenames.is_empty() && options.eval.is_none() && !io::stdin().is_terminal() {
+        filenames.push("-".to_string());
+    }
+    // an `-e` program is named for messages as the flag it came from
+    if options.eval.is_some() {
+        if !filenames.is_empty() {
+            usage();
+        }
+        filenames.push("-e".to_string());
+    }
+    if filenames.is_empty()
+        || (filenames.len() > 1 && !options.sequential)
+        || (options.carry_tape && !options.sequential)
+        || (options.sequential && options.input_lines.is_some())
+        || (options.sequential && options.meta_shortcut)
+        || (options.debug && (options.sequential || options.input_lines.is_some()))
+        || (options.debug && options.then_stdin && options.input_file.is_none())
+        || (!options.debug && (options.history_file.is_some() || options.history_cap.is_some()))
+        || (options.history_cap.is_some() && options.history_file.is_none())
+        || (options.output_file.is_some() && options.io_exec.is_some())
+        || (options.sample_every.is_some() && (options.profile || options.progress))
+    {
+        usage();
+    }
+    // an edge is only something a grid has
+    match (&mut options.grid, grid_wrap) {
+        (Some(grid), Some(wrap)) => grid.wrap = wrap,
+        (None, Some(_)) => usage(),
+        _ => {}
+    }
+    // a byte-exact filter: what `.` writes and nothing else, from input kept as it is in the file
+    if options.exact {
+        options.no_trailing_newline = true;
+        options.output_mode = OutputMode::Bytes;
+    }
+    options.filename = filenames.remove(0);
+    options.more_files = filenames;
+    #[cfg(all(feature = "plugins", unix))]
+    if options.plugins {
+        for (path, plugin) in plugins::discover() {
+            match plugin {
+                Ok(plugin) => options.loaded_plugins.push(plugin),
+                Err(e) => eprintln!("Unable to load plugin {}: {}", path.display(), e),
+            }
+        }
+    }
+    options
+}
+
+// note that `flag` sets something; it's an error if a different flag set it already, rather
+// than one quietly winning over the other
+fn claim<'a>(set_by: &mut Option<&'a str>, flag: &'a str) {
+    match set_by {
+        Some(other) if *other != flag => {
+            println!("`{}` can't be used with `{}`!", flag, other);
+            exit(1);
+        }
+        _ => *set_by = Some(flag),
+    }
+}
+
+// a duration such as `5s`, `1.5s`, `500ms` or `2m`; a bare number is in seconds
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let seconds = match unit {
+        "" | "s" => number,
+        "ms" => number / 1000.0,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+// everything `name` can be asked to do: the options for running a program, a line each, and
+// then the other commands
+pub fn print_usage(name: &str) {
+    println!(
+        "Usage: {0} [run] [options] [-e program | filename...]
+       {0} debug [options] [--history-file file] [--history-cap size] [filename]
+
+The program:
+  -e, --eval program           run this program rather than one from a file
+  --macros                     expand `@` macros before running
+  --opmap file.toml            take the commands from other characters
+  --plugins                    load extension ops from the plugin directory
+  --meta-shortcut              run what a self-interpreter is given directly
+  --verify-meta                and check that prints what the interpreter would
+
+Cells and tape:
+  --cell-bits N                cells of N bits, 1 to 64
+  --cell-width 8|16|32|64      the same, for the usual widths
+  --cell-modulus N             cells that wrap at N
+  --signed                     show cells as two's complement numbers
+  --overflow wrap|saturate|trap  what going past a cell's range does
+  --tape-size N                how many cells the tape starts with
+  --tape-edge wrap|error|grow  what the pointer does at the tape's ends
+  --tape-model unbounded-right|unbounded-both  a tape that grows at one end or both
+  --grid WxH                   lay the tape out in rows, with `^` and `v` moving between them
+  --grid-edge wrap|trap        what the pointer does at the grid's edges
+  --origin N                   the tape index shown as cell 0
+  --start-cell N               the cell the pointer starts on
+
+Input:
+  --input-file file            what `,` reads, rather than embedded or standard input
+  --then-stdin                 go on to standard input once that runs out
+  --input-lines file           run once for each line of the file
+  --separator text             what goes between the outputs of those runs
+  --strip-newline              drop the newline at the end of the input
+  --crlf                       read `\\r\\n` as `\\n`
+  --input-case upper|lower     change the case of the input
+  --hex-input                  read the input as hex bytes
+  --io-exec command            talk to a command: it reads the output and writes the input
+
+Output:
+  --output file                where `.` writes, rather than standard output
+  --output-mode latin1|unicode|bytes  how cell values are written
+  --no-trailing-newline        don't end the output with a newline
+  --exact                      the bytes written and nothing more, from input as it is
+  --print-cells a..b           show these cells at the end
+  --format dec|hex|ascii       how they're shown
+  --result-cells a..b          print these cells as one value at the end
+  --result-format u8|u16le|u32le|u64le|dec  how that value is read from them
+
+Reports:
+  --stats                      steps taken and time spent
+  --perf                       hardware counters too
+  --stats-format text|csv|tsv  how reports are written
+  --stats-out file             where the stats go
+  --profile                    how often each instruction ran
+  --sample-every N             profile by looking every N steps instead
+  --profile-out file           where the profile goes
+  --only-label name            trace and profile only the code under a label
+  --timeline-svg file.svg      draw the tape's reads and writes over time
+  --value-histogram            how the cells reached ended up, by value
+  --trace-loops                how often each loop ran and went round
+  --taint                      which output and cells came from input
+  --watch-cell N               log each write to a cell
+  --explain-opts               list the optimizations made before running
+  --progress                   show how far the run has got
+  --crash-dir dir              write what's needed to replay a run that traps
+
+Limits and checks:
+  --max-depth N                trap if loops nest deeper than this
+  --max-steps N                trap after this many steps
+  --timeout duration           trap once the run has taken this long, e.g. `5s`
+  --fail-on-nonzero            fail unless the current cell ends up zero
+  --fail-cell N                fail unless this cell ends up zero
+  --deterministic              refuse anything whose result could change between runs
+  --sandbox                    shut off the filesystem and network before running
+
+Several programs:
+  --sequential                 run each file in turn
+  --carry-tape                 each starting on the tape the one before left
+
+Debugging:
+  --history-file file          where steps too old to keep in memory go
+  --history-cap size           how big that file may get, e.g. `64M`
+
+Other commands:",
+        name
+    );
+    let commands = [
+        analysis::CHECK_USAGE,
+        analysis::STATS_USAGE,
+        lint::USAGE,
+        bracket::USAGE,
+        fmt::USAGE,
+        hash::USAGE,
+        compile::USAGE,
+        bfasm::USAGE,
+        macros::USAGE,
+        encoder::TEXT_USAGE,
+        encoder::FILE_USAGE,
+        dialect::USAGE,
+        quine_check::USAGE,
+        bench::USAGE,
+        corpus::USAGE,
+        "plugins",
+    ];
+    for command in commands {
+        println!("  {} {}", name, command);
+    }
+}
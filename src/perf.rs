@@ -0,0 +1,132 @@
+// hardware performance counters sampled through linux's perf_event_open
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::raw::{c_int, c_long, c_ulong};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+
+#[cfg(target_arch = "x86_64")]
+const SYS_PERF_EVENT_OPEN: c_long = 298;
+#[cfg(target_arch = "x86")]
+const SYS_PERF_EVENT_OPEN: c_long = 336;
+#[cfg(any(target_arch = "aarch64", target_arch = "riscv64"))]
+const SYS_PERF_EVENT_OPEN: c_long = 241;
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "x86",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+)))]
+compile_error!("the `perf` feature is not supported on this architecture");
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+
+const PERF_EVENT_IOC_ENABLE: c_ulong = 0x2400;
+const PERF_EVENT_IOC_DISABLE: c_ulong = 0x2401;
+const PERF_EVENT_IOC_RESET: c_ulong = 0x2403;
+
+// attr flag bits: start disabled, and only count user space so no privileges are needed
+const FLAG_DISABLED: u64 = 1 << 0;
+const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+// the first published layout of `struct perf_event_attr` (PERF_ATTR_SIZE_VER0)
+#[repr(C)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    bp_addr: u64,
+}
+
+extern "C" {
+    fn syscall(num: c_long, ...) -> c_long;
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+}
+
+// a set of running counters for the current thread
+pub struct PerfCounters {
+    counters: Vec<(&'static str, File)>,
+}
+
+impl PerfCounters {
+    // open and start every counter the kernel lets us have
+    pub fn start() -> io::Result<PerfCounters> {
+        let events = [
+            ("cycles", PERF_COUNT_HW_CPU_CYCLES),
+            ("instructions", PERF_COUNT_HW_INSTRUCTIONS),
+            ("cache-misses", PERF_COUNT_HW_CACHE_MISSES),
+        ];
+        let mut counters = Vec::new();
+        let mut last_error = None;
+        for (name, config) in events {
+            match open_counter(config) {
+                Ok(file) => counters.push((name, file)),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        if counters.is_empty() {
+            return Err(last_error.unwrap_or_else(|| io::Error::other("no counters")));
+        }
+        for (_, file) in &counters {
+            unsafe {
+                ioctl(file.as_raw_fd(), PERF_EVENT_IOC_RESET, 0);
+                ioctl(file.as_raw_fd(), PERF_EVENT_IOC_ENABLE, 0);
+            }
+        }
+        Ok(PerfCounters { counters })
+    }
+
+    // stop counting and read the final values
+    pub fn stop(self) -> Vec<(&'static str, u64)> {
+        self.counters
+            .into_iter()
+            .filter_map(|(name, mut file)| {
+                unsafe {
+                    ioctl(file.as_raw_fd(), PERF_EVENT_IOC_DISABLE, 0);
+                }
+                let mut buf = [0u8; 8];
+                file.read_exact(&mut buf).ok()?;
+                Some((name, u64::from_ne_bytes(buf)))
+            })
+            .collect()
+    }
+}
+
+fn open_counter(config: u64) -> io::Result<File> {
+    let attr = PerfEventAttr {
+        type_: PERF_TYPE_HARDWARE,
+        size: std::mem::size_of::<PerfEventAttr>() as u32,
+        config,
+        sample_period: 0,
+        sample_type: 0,
+        read_format: 0,
+        flags: FLAG_DISABLED | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV,
+        wakeup_events: 0,
+        bp_type: 0,
+        bp_addr: 0,
+    };
+    // pid 0 and cpu -1: this thread, on whichever cpu it runs
+    let fd = unsafe {
+        syscall(
+            SYS_PERF_EVENT_OPEN,
+            &attr as *const PerfEventAttr,
+            0 as c_int,
+            -1 as c_int,
+            -1 as c_int,
+            0 as c_ulong,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd as c_int) })
+}
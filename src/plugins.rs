@@ -0,0 +1,163 @@
+// extension ops loaded from shared libraries at run time
+//
+// a plugin is a shared library exporting this C ABI:
+//
+//     uint32_t    bf_plugin_abi_version(void);       /* must return 1 */
+//     const char *bf_plugin_name(void);
+//     const char *bf_plugin_ops(void);               /* the op characters it provides */
+//     uint32_t    bf_plugin_call(uint8_t op, uint32_t cell);
+//
+// `bf_plugin_call` gets the op and the current cell, and returns the cell's new value; cells
+// wider than 32 bits only pass their low 32 to it.
+// plugins are looked for in `$BF_PLUGIN_DIR`, or `./plugins` when that isn't set
+use std::{
+    env,
+    ffi::{CStr, CString},
+    fs,
+    os::raw::{c_char, c_int, c_void},
+    path::{Path, PathBuf},
+};
+
+use crate::{Cell, Interpreter};
+
+const ABI_VERSION: u32 = 1;
+const RTLD_NOW: c_int = 2;
+
+#[cfg(target_os = "macos")]
+const EXTENSION: &str = "dylib";
+#[cfg(not(target_os = "macos"))]
+const EXTENSION: &str = "so";
+
+#[link(name = "dl")]
+extern "C" {
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dlerror() -> *mut c_char;
+}
+
+type CallFn = extern "C" fn(u8, u32) -> u32;
+
+// a loaded plugin; libraries stay loaded for the life of the process
+pub struct Plugin {
+    pub name: String,
+    pub ops: Vec<char>,
+    call: CallFn,
+}
+
+fn last_error() -> String {
+    let error = unsafe { dlerror() };
+    if error.is_null() {
+        "unknown error".to_string()
+    } else {
+        unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+fn symbol(handle: *mut c_void, name: &str) -> Result<*mut c_void, String> {
+    let c_name = CString::new(name).unwrap_or_default();
+    let pointer = unsafe { dlsym(handle, c_name.as_ptr()) };
+    match pointer.is_null() {
+        true => Err(format!("missing `{}`", name)),
+        false => Ok(pointer),
+    }
+}
+
+fn string_from(handle: *mut c_void, name: &str) -> Result<String, String> {
+    let function: extern "C" fn() -> *const c_char =
+        unsafe { std::mem::transmute(symbol(handle, name)?) };
+    let text = function();
+    if text.is_null() {
+        return Err(format!("`{}` returned null", name));
+    }
+    Ok(unsafe { CStr::from_ptr(text) }
+        .to_string_lossy()
+        .into_owned())
+}
+
+impl Plugin {
+    pub fn load(path: &Path) -> Result<Plugin, String> {
+        let c_path =
+            CString::new(path.to_string_lossy().as_bytes()).map_err(|_| "bad path".to_string())?;
+        let handle = unsafe { dlopen(c_path.as_ptr(), RTLD_NOW) };
+        if handle.is_null() {
+            return Err(last_error());
+        }
+        let version: extern "C" fn() -> u32 =
+            unsafe { std::mem::transmute(symbol(handle, "bf_plugin_abi_version")?) };
+        if version() != ABI_VERSION {
+            return Err(format!(
+                "plugin ABI version {} (expected {})",
+                version(),
+                ABI_VERSION
+            ));
+        }
+        let name = string_from(handle, "bf_plugin_name")?;
+        let ops: Vec<char> = string_from(handle, "bf_plugin_ops")?.chars().collect();
+        if let Some(op) = ops
+            .iter()
+            .find(|&&op| !op.is_ascii() || "+-<>.,[]!".contains(op))
+        {
+            return Err(format!("`{}` can't be an extension op", op));
+        }
+        let call: CallFn = unsafe { std::mem::transmute(symbol(handle, "bf_plugin_call")?) };
+        Ok(Plugin { name, ops, call })
+    }
+
+    // bind every op of this plugin on the machine
+    pub fn install(&self, state: &mut Interpreter) {
+        for &op in &self.ops {
+            let call = self.call;
+            state.bind_host_function(
+                op,
+                Box::new(move |cell| call(op as u8, cell as u32) as Cell),
+            );
+        }
+    }
+}
+
+pub fn plugin_dir() -> PathBuf {
+    env::var_os("BF_PLUGIN_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("plugins"))
+}
+
+// every shared library in the plugin directory, loaded or with the reason it wouldn't load
+pub fn discover() -> Vec<(PathBuf, Result<Plugin, String>)> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(plugin_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|e| e == EXTENSION))
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let plugin = Plugin::load(&path);
+            (path, plugin)
+        })
+        .collect()
+}
+
+pub fn main(_name: &str, _args: &[String]) -> i32 {
+    let found = discover();
+    if found.is_empty() {
+        println!("No plugins in {}", plugin_dir().display());
+    }
+    for (path, plugin) in found {
+        match plugin {
+            Ok(plugin) => println!(
+                "{}  ops `{}`  {}",
+                plugin.name,
+                plugin.ops.iter().collect::<String>(),
+                path.display()
+            ),
+            Err(e) => println!("{}: {}", path.display(), e),
+        }
+    }
+    0
+}
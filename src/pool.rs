@@ -0,0 +1,149 @@
+// many machines in one process, for services running lots of users' programs: the pool goes
+// round them giving each a slice of steps at a time, so one busy program can't starve the
+// rest, and stops any that go past the limits every machine shares, e.g.
+//
+//     let limits = Limits { steps: Some(1_000_000), ..Limits::default() };
+//     let mut pool = Pool::new(10_000).limits(limits);
+//     let id = pool.add(Interpreter::parse(program, input)?).unwrap();
+//     pool.run_all();
+//     let state = pool.remove(id).unwrap();
+use crate::{step::RunStatus, Interpreter};
+
+// what every machine in a pool is held to; none means no limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub steps: Option<usize>,    // steps a machine can take in all
+    pub output: Option<usize>,   // bytes it can write; checked after each slice
+    pub machines: Option<usize>, // how many the pool holds at once
+}
+
+// a machine in a pool; ids of removed machines get used again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MachineId(usize);
+
+struct Entry {
+    state: Interpreter,
+    status: RunStatus,
+}
+
+pub struct Pool {
+    entries: Vec<Option<Entry>>,
+    slice: usize, // steps each machine gets per turn
+    limits: Limits,
+}
+
+impl Pool {
+    // a pool giving each machine `slice` steps a turn
+    pub fn new(slice: usize) -> Pool {
+        Pool {
+            entries: Vec::new(),
+            slice: slice.max(1),
+            limits: Limits::default(),
+        }
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Pool {
+        self.limits = limits;
+        self
+    }
+
+    // take `state` on to be run; none if the pool is full
+    pub fn add(&mut self, state: Interpreter) -> Option<MachineId> {
+        if self
+            .limits
+            .machines
+            .is_some_and(|limit| self.len() >= limit)
+        {
+            return None;
+        }
+        let entry = Some(Entry {
+            state,
+            status: RunStatus::Running,
+        });
+        match self.entries.iter().position(Option::is_none) {
+            Some(free) => {
+                self.entries[free] = entry;
+                Some(MachineId(free))
+            }
+            None => {
+                self.entries.push(entry);
+                Some(MachineId(self.entries.len() - 1))
+            }
+        }
+    }
+
+    // take a machine out of the pool, to read its results or to drop it
+    pub fn remove(&mut self, id: MachineId) -> Option<Interpreter> {
+        let entry = self.entries.get_mut(id.0)?.take()?;
+        Some(entry.state)
+    }
+
+    // a machine still in the pool, e.g. to look at its tape or feed it input
+    pub fn get(&self, id: MachineId) -> Option<&Interpreter> {
+        Some(&self.entries.get(id.0)?.as_ref()?.state)
+    }
+
+    pub fn get_mut(&mut self, id: MachineId) -> Option<&mut Interpreter> {
+        Some(&mut self.entries.get_mut(id.0)?.as_mut()?.state)
+    }
+
+    // where a machine got to as of its last turn
+    pub fn status(&self, id: MachineId) -> Option<RunStatus> {
+        Some(self.entries.get(id.0)?.as_ref()?.status)
+    }
+
+    // how many machines are in the pool
+    pub fn len(&self) -> usize {
+        self.entries.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // give every machine that hasn't halted one turn, in order, and say how many are still
+    // running; one waiting for input gets its turn too, in case input has been fed since
+    pub fn tick(&mut self) -> usize {
+        let (slice, limits) = (self.slice, self.limits);
+        let mut running = 0;
+        for entry in self.entries.iter_mut().flatten() {
+            if entry.status.finished() {
+                continue;
+            }
+            let state = &mut entry.state;
+            let budget = match limits.steps {
+                Some(limit) => slice.min(limit.saturating_sub(state.steps)),
+                None => slice,
+            };
+            entry.status = match budget {
+                0 => RunStatus::Running,
+                _ => state.run_for(budget),
+            };
+            if !entry.status.finished() {
+                let over = match (limits.steps, limits.output) {
+                    (Some(limit), _) if state.steps >= limit => {
+                        Some(format!("went past the limit of {} steps", limit))
+                    }
+                    (_, Some(limit)) if state.output_len > limit => {
+                        Some(format!("wrote more than the limit of {} bytes", limit))
+                    }
+                    _ => None,
+                };
+                match over {
+                    Some(message) => {
+                        state.trap(message);
+                        entry.status = RunStatus::Trapped;
+                    }
+                    None if entry.status == RunStatus::Running => running += 1,
+                    None => {}
+                }
+            }
+        }
+        running
+    }
+
+    // take turns until every machine has halted or is waiting for input
+    pub fn run_all(&mut self) {
+        while self.tick() > 0 {}
+    }
+}
@@ -0,0 +1,400 @@
+// parsing source bytes straight into operations, remembering where each one came from
+use std::{ops::Range, rc::Rc};
+
+use crate::{error::Error, Operations};
+
+// the byte range of an instruction in its source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+// a parsed program: one operation per source character, and that character's span. cloning
+// it, or starting a machine from it, shares the operations rather than copying them
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub operations: Rc<Vec<Operations>>,
+    pub spans: Rc<Vec<Span>>,
+    pub jumps: Rc<Vec<usize>>, // each bracket's partner; 0 for anything else
+    pub labels: Rc<Vec<Label>>,
+    pub source: Rc<[u8]>, // the text the spans point into
+}
+
+// a `=== label: name ===` comment, which names the code from there to the next label
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub name: String,
+    pub index: usize, // the first instruction after it
+}
+
+// the labels in `source`, in order, given where each instruction came from
+pub fn find_labels(source: &[u8], spans: &[Span]) -> Vec<Label> {
+    const OPEN: &[u8] = b"=== label:";
+    let find = |from: usize, needle: &[u8]| {
+        source[from..]
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .map(|at| from + at)
+    };
+    let mut labels = Vec::new();
+    let mut at = 0;
+    while let Some(start) = find(at, OPEN) {
+        at = start + OPEN.len();
+        let Some(end) = find(at, b"===") else {
+            break;
+        };
+        let name = &source[at..end];
+        if name.contains(&b'\n') {
+            continue;
+        }
+        at = end + 3;
+        let name = String::from_utf8_lossy(name).trim().to_string();
+        if !name.is_empty() {
+            labels.push(Label {
+                name,
+                index: spans.partition_point(|span| span.start < at),
+            });
+        }
+    }
+    labels
+}
+
+// each bracket's partner, worked out once so loops can jump straight to it; a `[` with no
+// partner points past the end and a `]` with none at itself
+pub fn jump_table(operations: &[Operations]) -> Vec<usize> {
+    let mut jumps = vec![0; operations.len()];
+    let mut open = Vec::new();
+    for (i, operation) in operations.iter().enumerate() {
+        match operation {
+            Operations::BracketLeft => open.push(i),
+            Operations::BracketRight => match open.pop() {
+                Some(start) => {
+                    jumps[start] = i;
+                    jumps[i] = start;
+                }
+                None => jumps[i] = i,
+            },
+            _ => {}
+        }
+    }
+    for start in open {
+        jumps[start] = operations.len();
+    }
+    jumps
+}
+
+// 1-based line and column of a byte offset, counting columns in characters
+pub fn line_col(source: &[u8], offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+    let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+    let column = String::from_utf8_lossy(&before[line_start..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+// the byte offset of 1-based `line` and `column`, counting columns in characters as
+// `line_col` does; none if the line is too short or the text too short
+pub fn offset_of(source: &[u8], line: usize, column: usize) -> Option<usize> {
+    let mut offset = match line {
+        0 => return None,
+        1 => 0,
+        _ => {
+            source
+                .iter()
+                .enumerate()
+                .filter(|(_, &b)| b == b'\n')
+                .nth(line - 2)?
+                .0
+                + 1
+        }
+    };
+    for _ in 1..column {
+        let window = &source[offset..(offset + 4).min(source.len())];
+        let len = match std::str::from_utf8(window) {
+            Ok(text) => text.chars().next().map_or(0, char::len_utf8),
+            Err(e) if e.valid_up_to() > 0 => std::str::from_utf8(&window[..e.valid_up_to()])
+                .ok()?
+                .chars()
+                .next()
+                .map_or(1, char::len_utf8),
+            Err(_) => 1,
+        };
+        if len == 0 || window[0] == b'\n' {
+            return None;
+        }
+        offset += len;
+    }
+    Some(offset)
+}
+
+// how deep `parse` lets loops nest. no real program comes near it, and a limit keeps a source
+// made of millions of `[`s from reaching the tools that work down through the nesting, like
+// `fmt`'s indentation, or from using a stack entry per bracket
+pub const MAX_NESTING: usize = 4096;
+
+impl Program {
+    // parse without going through a `String`; non-command bytes become comments, decoded as
+    // utf-8 where they can be, so extension ops can still be any character
+    pub fn parse(source: &[u8]) -> Result<Program, Error> {
+        Program::parse_with_limit(source, MAX_NESTING)
+    }
+
+    // `parse`, with loops allowed to nest `max_nesting` deep rather than `MAX_NESTING`
+    pub fn parse_with_limit(source: &[u8], max_nesting: usize) -> Result<Program, Error> {
+        let mut operations = Vec::with_capacity(source.len());
+        let mut spans = Vec::with_capacity(source.len());
+        let mut jumps = Vec::with_capacity(source.len());
+        let mut open = Vec::new(); // indices of `[`s not yet closed
+        let mut start = 0;
+        while start < source.len() {
+            let byte = source[start];
+            let (c, len) = if byte.is_ascii() {
+                (byte as char, 1)
+            } else {
+                let window = &source[start..(start + 4).min(source.len())];
+                let valid = match std::str::from_utf8(window) {
+                    Ok(text) => text,
+                    Err(e) => std::str::from_utf8(&window[..e.valid_up_to()]).unwrap_or(""),
+                };
+                match valid.chars().next() {
+                    Some(c) => (c, c.len_utf8()),
+                    None => (char::REPLACEMENT_CHARACTER, 1),
+                }
+            };
+            let index = operations.len();
+            jumps.push(0);
+            match c {
+                '[' if open.len() >= max_nesting => {
+                    let (line, column) = line_col(source, start);
+                    let message = format!("loops nested more than {} deep", max_nesting);
+                    return Err(Error::parse_at(line, column, message));
+                }
+                '[' => open.push(index),
+                ']' => match open.pop() {
+                    Some(partner) => {
+                        jumps[partner] = index;
+                        jumps[index] = partner;
+                    }
+                    None => {
+                        let (line, column) = line_col(source, start);
+                        return Err(Error::parse_at(line, column, "unmatched `]`"));
+                    }
+                },
+                _ => {}
+            }
+            operations.push(Operations::from_char(c));
+            spans.push(Span {
+                start,
+                end: start + len,
+            });
+            start += len;
+        }
+        if let Some(&index) = open.last() {
+            let (line, column) = line_col(source, spans[index].start);
+            return Err(Error::parse_at(line, column, "unmatched `[`"));
+        }
+        Ok(Program {
+            labels: Rc::new(find_labels(source, &spans)),
+            operations: Rc::new(operations),
+            spans: Rc::new(spans),
+            jumps: Rc::new(jumps),
+            source: Rc::from(source),
+        })
+    }
+}
+
+// building programs out of other programs; spans are kept as if the sources had been
+// put together the same way, so they still point at the right text
+impl Program {
+    pub fn empty() -> Program {
+        Program {
+            operations: Rc::new(Vec::new()),
+            spans: Rc::new(Vec::new()),
+            jumps: Rc::new(Vec::new()),
+            labels: Rc::new(Vec::new()),
+            source: Rc::from(&b""[..]),
+        }
+    }
+
+    // this program followed by `other`
+    pub fn concat(&self, other: &Program) -> Program {
+        let end = self.operations.len();
+        self.splice(end..end, other)
+            .expect("joining balanced programs keeps them balanced")
+    }
+
+    // this program `n` times over
+    pub fn repeat(&self, n: usize) -> Program {
+        (0..n).fold(Program::empty(), |program, _| program.concat(self))
+    }
+
+    // `other` put in place of the instructions in `range`, which may be empty to insert;
+    // fails if that would leave a bracket unmatched
+    pub fn splice(&self, range: Range<usize>, other: &Program) -> Result<Program, Error> {
+        if range.start > range.end || range.end > self.operations.len() {
+            return Err(Error::parse(format!(
+                "splice range {}..{} is outside the program",
+                range.start, range.end
+            )));
+        }
+        // the source bytes the replaced instructions came from
+        let byte_at = |i: usize| self.spans.get(i).map_or(self.source.len(), |s| s.start);
+        let (removed_start, removed_end) = (byte_at(range.start), byte_at(range.end));
+        // a span moved so the byte at `from` lands at `to`
+        let moved = |span: &Span, from: usize, to: usize| Span {
+            start: span.start - from + to,
+            end: span.end - from + to,
+        };
+
+        let mut operations = self.operations[..range.start].to_vec();
+        operations.extend_from_slice(&other.operations);
+        operations.extend_from_slice(&self.operations[range.end..]);
+        let mut spans = self.spans[..range.start].to_vec();
+        spans.extend(other.spans.iter().map(|s| moved(s, 0, removed_start)));
+        let tail = removed_start + other.source.len();
+        spans.extend(
+            self.spans[range.end..]
+                .iter()
+                .map(|s| moved(s, removed_end, tail)),
+        );
+
+        let mut depth = 0usize;
+        for operation in &operations {
+            match operation {
+                Operations::BracketLeft => depth += 1,
+                Operations::BracketRight if depth == 0 => {
+                    return Err(Error::parse("the splice leaves an unmatched `]`"))
+                }
+                Operations::BracketRight => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth > 0 {
+            return Err(Error::parse("the splice leaves an unmatched `[`"));
+        }
+        let source: Rc<[u8]> = [
+            &self.source[..removed_start],
+            &other.source[..],
+            &self.source[removed_end..],
+        ]
+        .concat()
+        .into();
+        Ok(Program {
+            jumps: Rc::new(jump_table(&operations)),
+            labels: Rc::new(find_labels(&source, &spans)),
+            operations: Rc::new(operations),
+            spans: Rc::new(spans),
+            source,
+        })
+    }
+}
+
+// a loop in the program, by the instruction indices of its brackets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    pub start: usize, // the `[`
+    pub end: usize,   // its `]`
+    pub children: Vec<Loop>,
+}
+
+// looking at the structure of a parsed program, for analyzers and editors
+impl Program {
+    // every instruction, comments included, with where it came from
+    pub fn instructions(&self) -> impl Iterator<Item = (Operations, Span)> + '_ {
+        self.operations
+            .iter()
+            .copied()
+            .zip(self.spans.iter().copied())
+    }
+
+    pub fn span(&self, index: usize) -> Option<Span> {
+        self.spans.get(index).copied()
+    }
+
+    // the bracket paired with the one at `index`, or none if it isn't a bracket
+    pub fn matching(&self, index: usize) -> Option<usize> {
+        matching(&self.operations, index)
+    }
+
+    // the top-level loops, each holding the loops nested directly inside it
+    pub fn loops(&self) -> Vec<Loop> {
+        let mut stack: Vec<Loop> = Vec::new();
+        let mut top = Vec::new();
+        for (i, operation) in self.operations.iter().enumerate() {
+            match operation {
+                Operations::BracketLeft => stack.push(Loop {
+                    start: i,
+                    end: i,
+                    children: Vec::new(),
+                }),
+                Operations::BracketRight => {
+                    if let Some(mut done) = stack.pop() {
+                        done.end = i;
+                        match stack.last_mut() {
+                            Some(parent) => parent.children.push(done),
+                            None => top.push(done),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        top
+    }
+
+    // the `[`..`]` pairs around instruction `index`, outermost first
+    pub fn enclosing_loops(&self, index: usize) -> Vec<(usize, usize)> {
+        enclosing_loops(&self.operations, index)
+    }
+}
+
+// the bracket paired with the one at `index` in `operations`
+pub fn matching(operations: &[Operations], index: usize) -> Option<usize> {
+    let forward = match operations.get(index)? {
+        Operations::BracketLeft => true,
+        Operations::BracketRight => false,
+        _ => return None,
+    };
+    let mut depth = 0usize;
+    let mut i = index;
+    loop {
+        match (operations.get(i)?, forward) {
+            (Operations::BracketLeft, true) | (Operations::BracketRight, false) => depth += 1,
+            (Operations::BracketLeft, false) | (Operations::BracketRight, true) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i = match forward {
+            true => i + 1,
+            false => i.checked_sub(1)?,
+        };
+    }
+}
+
+// the `[`..`]` pairs around instruction `index` in `operations`, outermost first
+pub fn enclosing_loops(operations: &[Operations], index: usize) -> Vec<(usize, usize)> {
+    let mut open = Vec::new();
+    for (i, operation) in operations.iter().enumerate().take(index) {
+        match operation {
+            Operations::BracketLeft => open.push(i),
+            Operations::BracketRight => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    open.into_iter()
+        .filter_map(|start| Some((start, matching(operations, start)?)))
+        .collect()
+}
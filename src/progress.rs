@@ -0,0 +1,148 @@
+// periodic callbacks while a program runs, and the `--progress` status line and sampling
+// profiler built on them
+use std::cell::RefCell;
+use std::io::{self, IsTerminal, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::Interpreter;
+
+// how often `--progress` looks at the clock, and how often it redraws
+const CHECK_EVERY: usize = 1 << 16;
+const REDRAW_EVERY: Duration = Duration::from_millis(200);
+
+// what a heartbeat callback gets told about the run so far
+#[derive(Debug, Clone, Copy)]
+pub struct Heartbeat {
+    pub steps: usize,
+    pub index: usize,      // the instruction that ran last
+    pub pointer: isize,    // relative to the origin
+    pub output_len: usize, // bytes written so far
+    pub finished: bool,    // the last beat, sent once the program has halted
+}
+
+pub type HeartbeatFn = Box<dyn FnMut(&Heartbeat)>;
+
+// a registered callback and when it's next due
+pub struct HeartbeatHook {
+    every: usize,
+    next: usize,
+    callback: HeartbeatFn,
+}
+
+impl Interpreter {
+    // call `callback` every `every` steps, and once more when the program halts
+    pub fn on_heartbeat(&mut self, every: usize, callback: impl FnMut(&Heartbeat) + 'static) {
+        let every = every.max(1);
+        self.heartbeat = Some(HeartbeatHook {
+            every,
+            next: self.steps + every,
+            callback: Box::new(callback),
+        });
+    }
+
+    pub fn beat(&mut self, finished: bool) {
+        if let Some(hook) = &mut self.heartbeat {
+            if !finished && self.steps < hook.next {
+                return;
+            }
+            hook.next = self.steps + hook.every;
+            let heartbeat = Heartbeat {
+                steps: self.steps,
+                index: self.ran_last,
+                pointer: self.memory.idx as isize - self.memory.origin as isize,
+                output_len: self.output_len,
+                finished,
+            };
+            (hook.callback)(&heartbeat);
+        }
+    }
+}
+
+// a live status line on stderr for long runs
+pub struct Progress {
+    start: Instant,
+    last_draw: Instant,
+    limit: Option<usize>, // the step limit, for an estimated finish
+    in_place: bool,       // redraw one line on a terminal, print lines otherwise
+}
+
+impl Progress {
+    pub fn new(limit: Option<usize>) -> Progress {
+        let now = Instant::now();
+        Progress {
+            start: now,
+            last_draw: now,
+            limit,
+            in_place: io::stderr().is_terminal(),
+        }
+    }
+
+    // show progress on `state` until it halts
+    pub fn attach(mut self, state: &mut Interpreter) {
+        state.on_heartbeat(CHECK_EVERY, move |heartbeat| self.beat(heartbeat));
+    }
+
+    fn beat(&mut self, heartbeat: &Heartbeat) {
+        if heartbeat.finished {
+            // take the status line away once the run is over
+            if self.in_place && self.last_draw != self.start {
+                let _ = write!(io::stderr(), "\r\x1b[K");
+            }
+        } else if self.last_draw.elapsed() >= REDRAW_EVERY {
+            self.last_draw = Instant::now();
+            self.draw(heartbeat);
+        }
+    }
+
+    fn draw(&self, heartbeat: &Heartbeat) {
+        let steps = heartbeat.steps;
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            steps as f64 / elapsed
+        } else {
+            0.0
+        };
+        let mut line = format!(
+            "{} steps  {:.0} steps/s  {:.1}s elapsed  cell {}  {} bytes out",
+            steps, rate, elapsed, heartbeat.pointer, heartbeat.output_len
+        );
+        if let Some(limit) = self.limit.filter(|_| rate > 0.0) {
+            let remaining = limit.saturating_sub(steps) as f64 / rate;
+            line.push_str(&format!("  ~{:.1}s to the step limit", remaining));
+        }
+        let mut stderr = io::stderr();
+        let _ = match self.in_place {
+            true => write!(stderr, "\r{}\x1b[K", line),
+            false => writeln!(stderr, "{}", line),
+        };
+    }
+}
+
+// `--sample-every`: a profile made by noting the instruction running every so many steps
+// instead of counting every one, which keeps the fused instructions and so costs next to
+// nothing; a fused instruction's steps all go to it, so the counts are only roughly right
+#[derive(Clone)]
+pub struct Sampler(Rc<RefCell<Vec<u64>>>);
+
+impl Sampler {
+    // start sampling `state` every `every` steps; this takes the place of any heartbeat
+    pub fn attach(state: &mut Interpreter, every: usize) -> Sampler {
+        let sampler = Sampler(Rc::new(RefCell::new(vec![0; state.operations.len()])));
+        let counts = Rc::clone(&sampler.0);
+        state.on_heartbeat(every, move |heartbeat| {
+            if let (false, Some(count)) = (
+                heartbeat.finished,
+                counts.borrow_mut().get_mut(heartbeat.index),
+            ) {
+                *count += 1;
+            }
+        });
+        sampler
+    }
+
+    // the samples each instruction got
+    pub fn counts(&self) -> Vec<u64> {
+        self.0.borrow().clone()
+    }
+}
@@ -0,0 +1,90 @@
+// checking that a program prints exactly its own source
+use std::{io::Read, time::Duration};
+
+use crate::{
+    program::{line_col, Program},
+    split_source,
+    stream::OutputReader,
+};
+
+// how many steps a program gets to print itself, unless its budget says otherwise
+pub const DEFAULT_STEPS: usize = 100_000_000;
+
+// how far a program may run: a program that doesn't get through its printing within these
+// isn't a quine
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    pub steps: usize,
+    pub time: Option<Duration>,
+}
+
+impl Default for Budget {
+    fn default() -> Budget {
+        Budget {
+            steps: DEFAULT_STEPS,
+            time: None,
+        }
+    }
+}
+
+// how output and source are tidied before they're compared
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Normalization {
+    pub trim: bool,          // ignore whitespace at the start and end
+    pub commands_only: bool, // compare only the eight brainfuck commands
+}
+
+impl Normalization {
+    fn apply(&self, text: &str) -> String {
+        let text = if self.trim { text.trim() } else { text };
+        match self.commands_only {
+            true => text.chars().filter(|c| "+-<>.,[]".contains(*c)).collect(),
+            false => text.to_string(),
+        }
+    }
+}
+
+// run the program in `source` (a whole source file, embedded input and all) and compare
+// what it prints with `source`, within `budget`; says where they first differ if they do, or
+// why the program stopped if it didn't finish
+pub fn check(source: &str, normalization: Normalization, budget: Budget) -> Result<(), String> {
+    let (program, inputs) = split_source(source);
+    Program::parse(program.as_bytes()).map_err(|e| e.to_string())?;
+    let expected = normalization.apply(source);
+
+    // stop reading a little past the expected length, so endless printers still finish
+    let mut output = Vec::new();
+    let limit = source.len() as u64 + 1024;
+    let mut reader =
+        OutputReader::new(program, inputs.first().copied().unwrap_or("")).step_limit(budget.steps);
+    if let Some(time) = budget.time {
+        reader = reader.time_limit(time);
+    }
+    reader
+        .take(limit)
+        .read_to_end(&mut output)
+        .map_err(|e| format!("the program didn't finish: {}", e))?;
+    let actual = normalization.apply(&String::from_utf8_lossy(&output));
+
+    let differs = expected
+        .char_indices()
+        .zip(actual.chars())
+        .find(|((_, want), got)| want != got);
+    let offset = match differs {
+        Some(((offset, _), _)) => offset,
+        None if expected.len() == actual.len() => return Ok(()),
+        None => expected.len().min(actual.len()),
+    };
+    let (line, column) = line_col(expected.as_bytes(), offset);
+    let shown = |text: &str| match text[offset..].chars().next() {
+        Some(c) => format!("{:?}", c),
+        None => "the end".to_string(),
+    };
+    Err(format!(
+        "output differs at line {}, column {}: expected {}, got {}",
+        line,
+        column,
+        shown(&expected),
+        shown(&actual)
+    ))
+}
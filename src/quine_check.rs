@@ -0,0 +1,58 @@
+// `quine-check`: the command line over the library's quine check
+use std::fs;
+
+use brainfuck_jit::quine::{check, Budget, Normalization};
+
+pub const USAGE: &str =
+    "quine-check [--trim] [--commands-only] [--max-steps N] [--timeout duration] [filename]";
+
+pub fn main(name: &str, args: &[String]) -> i32 {
+    let usage = || {
+        println!("Usage: {} {}", name, USAGE);
+        1
+    };
+    let mut normalization = Normalization::default();
+    let mut budget = Budget::default();
+    let mut files = Vec::new();
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--trim" => normalization.trim = true,
+            "--commands-only" => normalization.commands_only = true,
+            "--max-steps" => match rest.next().and_then(|steps| steps.parse().ok()) {
+                Some(steps) => budget.steps = steps,
+                None => return usage(),
+            },
+            "--timeout" => match rest
+                .next()
+                .and_then(|time| crate::options::parse_duration(time))
+            {
+                Some(time) => budget.time = Some(time),
+                None => return usage(),
+            },
+            _ if arg.starts_with("--") => return usage(),
+            _ => files.push(arg),
+        }
+    }
+    let filename = match files[..] {
+        [filename] => filename,
+        _ => return usage(),
+    };
+    let contents = match fs::read_to_string(filename) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Unable to read {}: {}", filename, e);
+            return 1;
+        }
+    };
+    match check(&contents, normalization, budget) {
+        Ok(()) => {
+            println!("{} is a quine", filename);
+            0
+        }
+        Err(e) => {
+            println!("{} is not a quine: {}", filename, e);
+            1
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// tabular reports (run statistics, profiles) as aligned text, csv or tsv
+use std::fmt::Write;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Csv,
+    Tsv,
+}
+
+impl ReportFormat {
+    pub fn parse(name: &str) -> Option<ReportFormat> {
+        match name {
+            "text" => Some(ReportFormat::Text),
+            "csv" => Some(ReportFormat::Csv),
+            "tsv" => Some(ReportFormat::Tsv),
+            _ => None,
+        }
+    }
+}
+
+// named columns and rows of values
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&'static str]) -> Table {
+        Table {
+            headers: headers.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(&mut self, values: Vec<String>) {
+        self.rows.push(values);
+    }
+
+    // two-column tables read as `key: value` in text; wider ones get aligned columns
+    pub fn render(&self, format: ReportFormat) -> String {
+        let mut out = String::new();
+        match format {
+            ReportFormat::Text if self.headers.len() == 2 => {
+                for row in &self.rows {
+                    let _ = writeln!(out, "{}: {}", row[0], row[1]);
+                }
+            }
+            ReportFormat::Text => {
+                let widths: Vec<usize> = (0..self.headers.len())
+                    .map(|i| {
+                        self.rows
+                            .iter()
+                            .map(|row| row[i].len())
+                            .chain([self.headers[i].len()])
+                            .max()
+                            .unwrap_or(0)
+                    })
+                    .collect();
+                let line = |cells: Vec<&str>| {
+                    let padded: Vec<String> = cells
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, width)| format!("{:>width$}", cell, width = width))
+                        .collect();
+                    padded.join("  ")
+                };
+                let _ = writeln!(out, "{}", line(self.headers.clone()));
+                for row in &self.rows {
+                    let _ = writeln!(out, "{}", line(row.iter().map(String::as_str).collect()));
+                }
+            }
+            ReportFormat::Csv | ReportFormat::Tsv => {
+                let separator = if format == ReportFormat::Csv {
+                    ","
+                } else {
+                    "\t"
+                };
+                let field = |value: &str| match format {
+                    ReportFormat::Csv if value.contains([',', '"', '\n']) => {
+                        format!("\"{}\"", value.replace('"', "\"\""))
+                    }
+                    ReportFormat::Tsv => value.replace(['\t', '\n'], " "),
+                    _ => value.to_string(),
+                };
+                let header: Vec<String> = self.headers.iter().map(|h| field(h)).collect();
+                let _ = writeln!(out, "{}", header.join(separator));
+                for row in &self.rows {
+                    let cells: Vec<String> = row.iter().map(|v| field(v)).collect();
+                    let _ = writeln!(out, "{}", cells.join(separator));
+                }
+            }
+        }
+        out
+    }
+}
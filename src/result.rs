@@ -0,0 +1,42 @@
+// running a whole program and keeping its output as bytes, for embedders
+use std::{mem, str::Utf8Error};
+
+use crate::{stream::Pending, trap::Trap, usage::ResourceUsage, Halt, Interpreter};
+
+// everything a finished run produced; `output` is the exact bytes written, which with
+// `OutputMode::Bytes` is one byte per `.`
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    pub output: Vec<u8>,
+    pub halt: Halt,
+    pub trap: Option<Trap>, // set when `halt` is `Trapped`
+    pub usage: ResourceUsage,
+}
+
+impl RunResult {
+    // the output as text, with bytes that aren't utf-8 replaced by U+FFFD
+    pub fn output_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+
+    // the output as text, or an error if it isn't valid utf-8
+    pub fn output_str(&self) -> Result<&str, Utf8Error> {
+        std::str::from_utf8(&self.output)
+    }
+}
+
+impl Interpreter {
+    // run to the end, collecting the output instead of writing it wherever it was going
+    pub fn run_collect(&mut self) -> RunResult {
+        let pending = Pending::default();
+        let output = mem::replace(&mut self.output, Box::new(pending.clone()));
+        self.run();
+        self.output = output;
+        RunResult {
+            output: pending.take(),
+            halt: self.halt.unwrap_or(Halt::End),
+            trap: self.trap.clone(),
+            usage: self.usage(),
+        }
+    }
+}
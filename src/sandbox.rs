@@ -0,0 +1,144 @@
+// confining the process with linux's landlock before running an untrusted program
+//
+// once applied, the process (and anything it starts) can't read, execute or change any
+// file except by creating or overwriting files in the directories given. kernels new enough
+// also stop it using tcp (landlock abi 4) and signalling other processes or reaching their
+// abstract unix sockets (abi 6). a kernel without landlock is an error, never a silent no-op
+use std::fs::File;
+use std::io;
+use std::os::raw::{c_int, c_long, c_ulong};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::Path;
+
+// the landlock syscalls have the same numbers on every architecture
+const SYS_LANDLOCK_CREATE_RULESET: c_long = 444;
+const SYS_LANDLOCK_ADD_RULE: c_long = 445;
+const SYS_LANDLOCK_RESTRICT_SELF: c_long = 446;
+
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+const LANDLOCK_RULE_PATH_BENEATH: c_int = 1;
+const PR_SET_NO_NEW_PRIVS: c_int = 38;
+
+// filesystem rights, by the abi version that added them
+const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+const ACCESS_FS_V1: u64 = (1 << 13) - 1;
+const ACCESS_FS_REFER: u64 = 1 << 13; // abi 2
+const ACCESS_FS_TRUNCATE: u64 = 1 << 14; // abi 3
+const ACCESS_FS_IOCTL_DEV: u64 = 1 << 15; // abi 5
+
+const ACCESS_NET_BIND_TCP: u64 = 1 << 0; // abi 4
+const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1;
+
+const SCOPE_ABSTRACT_UNIX_SOCKET: u64 = 1 << 0; // abi 6
+const SCOPE_SIGNAL: u64 = 1 << 1;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+    handled_access_net: u64,
+    scoped: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+extern "C" {
+    fn syscall(num: c_long, ...) -> c_long;
+    fn prctl(option: c_int, arg2: c_ulong, arg3: c_ulong, arg4: c_ulong, arg5: c_ulong) -> c_int;
+}
+
+fn check(result: c_long, what: &str) -> io::Result<c_long> {
+    match result {
+        -1 => {
+            let error = io::Error::last_os_error();
+            Err(io::Error::new(error.kind(), format!("{}: {}", what, error)))
+        }
+        _ => Ok(result),
+    }
+}
+
+// restrict this process for good; `writable` directories may have files created or
+// overwritten in them, for reports written after the run
+pub fn apply(writable: &[&Path]) -> io::Result<()> {
+    let abi = check(
+        unsafe {
+            syscall(
+                SYS_LANDLOCK_CREATE_RULESET,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        },
+        "landlock is unavailable",
+    )?;
+
+    let mut handled_fs = ACCESS_FS_V1;
+    if abi >= 2 {
+        handled_fs |= ACCESS_FS_REFER;
+    }
+    if abi >= 3 {
+        handled_fs |= ACCESS_FS_TRUNCATE;
+    }
+    if abi >= 5 {
+        handled_fs |= ACCESS_FS_IOCTL_DEV;
+    }
+    let attr = RulesetAttr {
+        handled_access_fs: handled_fs,
+        handled_access_net: if abi >= 4 {
+            ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP
+        } else {
+            0
+        },
+        scoped: if abi >= 6 {
+            SCOPE_ABSTRACT_UNIX_SOCKET | SCOPE_SIGNAL
+        } else {
+            0
+        },
+    };
+    // older kernels reject fields they don't know about
+    let size = match abi {
+        1..=3 => 8,
+        4 | 5 => 16,
+        _ => std::mem::size_of::<RulesetAttr>(),
+    };
+    let ruleset = check(
+        unsafe { syscall(SYS_LANDLOCK_CREATE_RULESET, &attr, size, 0u32) },
+        "unable to create a landlock ruleset",
+    )?;
+    let ruleset = unsafe { File::from_raw_fd(ruleset as c_int) };
+
+    let allowed = (ACCESS_FS_WRITE_FILE | ACCESS_FS_MAKE_REG | ACCESS_FS_TRUNCATE) & handled_fs;
+    for dir in writable {
+        let dir = File::open(dir)?;
+        let rule = PathBeneathAttr {
+            allowed_access: allowed,
+            parent_fd: dir.as_raw_fd(),
+        };
+        check(
+            unsafe {
+                syscall(
+                    SYS_LANDLOCK_ADD_RULE,
+                    ruleset.as_raw_fd(),
+                    LANDLOCK_RULE_PATH_BENEATH,
+                    &rule,
+                    0u32,
+                )
+            },
+            "unable to add a landlock rule",
+        )?;
+    }
+
+    check(
+        unsafe { prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } as c_long,
+        "unable to set no_new_privs",
+    )?;
+    check(
+        unsafe { syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset.as_raw_fd(), 0u32) },
+        "unable to apply the landlock ruleset",
+    )?;
+    Ok(())
+}
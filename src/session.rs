@@ -0,0 +1,42 @@
+// running more code on a machine that has already run, for repl-style hosts
+use std::rc::Rc;
+
+use crate::{error::Error, program::find_labels, Interpreter, Operations};
+
+impl Interpreter {
+    // append `fragment` to the program and run it, keeping the tape, pointer and i/o as they
+    // are; the fragment's brackets have to match among themselves. a fragment run before isn't
+    // parsed again, and only the new part of the program is compiled
+    pub fn run_fragment(&mut self, fragment: &str) -> Result<(), Error> {
+        let parsed = self.fragments.get(fragment.as_bytes())?;
+        let start = self.operations.len();
+        let offset = self.source.len();
+        let compiled = self.take_compiled();
+
+        let operations = Rc::make_mut(&mut self.operations);
+        operations.extend(parsed.operations.iter().map(|&operation| match operation {
+            Operations::Comment(c) if self.host_functions.contains_key(&c) => Operations::Host(c),
+            _ => operation,
+        }));
+        if self.spans.len() == start {
+            Rc::make_mut(&mut self.spans).extend(parsed.spans.iter().map(|span| {
+                let mut span = *span;
+                span.start += offset;
+                span.end += offset;
+                span
+            }));
+            self.source = [&self.source[..], &parsed.source[..]].concat().into();
+            self.labels = Rc::new(find_labels(&self.source, &self.spans));
+        }
+        Rc::make_mut(&mut self.jumps).extend(parsed.jumps.iter().map(|&jump| jump + start));
+        if let Some(profile) = &mut self.profile {
+            profile.resize(self.operations.len(), 0);
+        }
+        self.compile_appended(compiled, start);
+
+        self.idx = start;
+        self.trap = None;
+        self.halt = None;
+        self.try_run()
+    }
+}
@@ -0,0 +1,208 @@
+// executing one instruction at a time; `run` and the output reader are built on this
+use std::io::Write;
+use std::time::Instant;
+
+use crate::{trap::Trap, Cell, Halt, Interpreter, Operations};
+
+// what an instruction did, in terms of cells numbered from the origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Nothing,                            // a comment, or a bracket that fell through
+    Pointer(isize),                     // the pointer moved to this cell
+    Cell { cell: isize, value: Cell },  // a cell was changed to this value
+    Input { cell: isize, value: Cell }, // a byte of input was stored
+    Output(Cell),                       // this cell value was written out
+    Jump(usize),                        // control went to this instruction
+}
+
+// where a budgeted run got to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Running,    // the budget ran out; there's more to run
+    Halted,     // the program ended
+    NeedsInput, // `,` is waiting on input that isn't there yet
+    Trapped,    // the run stopped on an error, which is in `trap`
+}
+
+impl RunStatus {
+    // whether there's nothing more to run, however it ended
+    pub fn finished(self) -> bool {
+        matches!(self, RunStatus::Halted | RunStatus::Trapped)
+    }
+}
+
+// the result of one `step`
+#[derive(Debug, Clone, Copy)]
+pub struct StepOutcome {
+    pub index: usize,                  // the instruction that ran
+    pub operation: Option<Operations>, // none if the program had already halted
+    pub effect: Effect,
+    pub halted: bool, // nothing is left to run
+}
+
+// how many steps go by between looks at the clock for `time_limit`
+const CLOCK_EVERY: usize = 1 << 16;
+
+impl Interpreter {
+    // run the next instruction; a trap is returned as the error, and also stays on the machine
+    pub fn step(&mut self) -> Result<StepOutcome, Trap> {
+        let index = self.idx;
+        let operation = match self.operations.get(index) {
+            Some(&operation) => operation,
+            None => {
+                return Ok(StepOutcome {
+                    index,
+                    operation: None,
+                    effect: Effect::Nothing,
+                    halted: true,
+                })
+            }
+        };
+        self.check_limits()?;
+        self.needs_input = false;
+        match self.history.is_some() {
+            true => {
+                let (undo, input_len) = (self.undo_point(), self.input_len);
+                self.execute();
+                if !self.needs_input {
+                    self.remember(undo, input_len);
+                }
+            }
+            false => self.execute(),
+        }
+        if let Some(trap) = &self.trap {
+            return Err(trap.clone());
+        }
+
+        let cell = self.memory.idx as isize - self.memory.origin as isize;
+        let value = self.memory.bytearray[self.memory.idx];
+        let effect = match operation {
+            Operations::MoveLeft
+            | Operations::MoveRight
+            | Operations::Up(_)
+            | Operations::Down(_) => Effect::Pointer(cell),
+            Operations::Add | Operations::Subtract | Operations::Host(_) | Operations::Clear(_) => {
+                Effect::Cell { cell, value }
+            }
+            Operations::Input if self.needs_input => Effect::Nothing,
+            Operations::Input => Effect::Input { cell, value },
+            Operations::Output | Operations::OutputDecimal(_) => Effect::Output(value),
+            Operations::BracketLeft | Operations::BracketRight if self.idx != index + 1 => {
+                Effect::Jump(self.idx)
+            }
+            Operations::BracketLeft | Operations::BracketRight | Operations::Comment(_) => {
+                Effect::Nothing
+            }
+        };
+        Ok(StepOutcome {
+            index,
+            operation: Some(operation),
+            effect,
+            halted: self.idx >= self.operations.len(),
+        })
+    }
+
+    // stop the run with a trap if it's taken all the steps `max_steps` allows, or run for
+    // longer than `time_limit`
+    pub(crate) fn check_limits(&mut self) -> Result<(), Trap> {
+        let message = match (self.max_steps, self.time_limit) {
+            (Some(limit), _) if self.steps >= limit => {
+                format!("step limit exceeded after {} instructions", self.steps)
+            }
+            (_, Some(limit)) => {
+                // the clock starts with the first step, and is only looked at now and then
+                let (start, next) = self.clock.get_or_insert((Instant::now(), 0));
+                if self.steps < *next {
+                    return Ok(());
+                }
+                *next = self.steps.saturating_add(CLOCK_EVERY);
+                if start.elapsed() < limit {
+                    return Ok(());
+                }
+                format!(
+                    "the time limit of {:?} ran out after {} instructions",
+                    limit, self.steps
+                )
+            }
+            _ => return Ok(()),
+        };
+        self.trap(message);
+        Err(self.trap.clone().expect("a trap was just set"))
+    }
+
+    // whether `steps` more can be taken in one go without going past `max_steps`
+    pub(crate) fn within_budget(&self, steps: usize) -> bool {
+        self.max_steps
+            .is_none_or(|limit| self.steps.saturating_add(steps) <= limit)
+    }
+
+    // run up to `steps` more steps and say why it stopped, so a host can share its time
+    // between the program and other work, such as drawing a frame; a `,` with no input ready
+    // (its reader gives `WouldBlock`, as a `Feed` does) pauses the run instead of trapping
+    pub fn run_for(&mut self, steps: usize) -> RunStatus {
+        let target = self.steps.saturating_add(steps);
+        let status = loop {
+            if self.steps >= target {
+                break RunStatus::Running;
+            }
+            match self.step() {
+                Err(_) => break RunStatus::Trapped,
+                Ok(outcome) if outcome.halted => break RunStatus::Halted,
+                Ok(_) if self.needs_input => break RunStatus::NeedsInput,
+                Ok(_) => {}
+            }
+        };
+        if let Err(e) = self.output.flush() {
+            self.output_failed(e);
+        }
+        if status == RunStatus::Halted && self.halt.is_none() {
+            self.halt = Some(Halt::End);
+        }
+        // a flush that failed may have stopped the run after all
+        self.status()
+    }
+
+    // run until the program halts or a `,` is left waiting for input, which can then be fed
+    // and the run resumed; this goes through the fused instructions unless something is
+    // watching step by step, so it's as quick as `run`
+    pub fn resume(&mut self) -> RunStatus {
+        self.needs_input = false;
+        if self.watched() {
+            while let Ok(outcome) = self.step() {
+                if outcome.halted || self.needs_input {
+                    break;
+                }
+                self.beat(false);
+            }
+        } else {
+            self.run_compiled();
+        }
+        if let Err(e) = self.output.flush() {
+            self.output_failed(e);
+        }
+        if self.needs_input {
+            return RunStatus::NeedsInput;
+        }
+        if self.halt.is_none() {
+            self.halt = Some(Halt::End);
+        }
+        self.beat(true);
+        self.status()
+    }
+
+    // where the run has got to, e.g. for a host stepping it a few instructions a frame
+    pub fn status(&self) -> RunStatus {
+        match self.halt {
+            Some(Halt::Trapped) => RunStatus::Trapped,
+            Some(_) => RunStatus::Halted,
+            None if self.idx >= self.operations.len() => RunStatus::Halted,
+            None if self.needs_input => RunStatus::NeedsInput,
+            None => RunStatus::Running,
+        }
+    }
+
+    // whether the last step was a `,` left waiting for input
+    pub fn needs_input(&self) -> bool {
+        self.needs_input
+    }
+}
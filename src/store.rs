@@ -0,0 +1,126 @@
+// parsed programs kept by the hash of their source, for hosts that see the same programs
+// again and again (a `Pool` behind a service, say): a program submitted before is handed back
+// without parsing it or storing it twice, and the least recently used go once the store is
+// over its size
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    mem,
+};
+
+use crate::{
+    error::Error,
+    program::{Program, Span},
+    Operations,
+};
+
+struct Stored {
+    program: Program,
+    size: usize,    // roughly the bytes it takes up
+    last_used: u64, // by the store's count of lookups
+}
+
+pub struct ProgramStore {
+    programs: HashMap<u64, Stored>,
+    capacity: usize, // bytes
+    used: usize,
+    lookups: u64,
+    hits: u64,
+}
+
+// the address a source is stored under
+pub fn content_hash(source: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+// roughly what a parsed `source` takes up: its text, plus the operation, span and jump each
+// character becomes
+fn size_of(source: &[u8]) -> usize {
+    let per_byte = mem::size_of::<Operations>() + mem::size_of::<Span>() + mem::size_of::<usize>();
+    source.len() * (per_byte + 1)
+}
+
+impl ProgramStore {
+    // a store holding up to about `capacity` bytes of programs
+    pub fn new(capacity: usize) -> ProgramStore {
+        ProgramStore {
+            programs: HashMap::new(),
+            capacity,
+            used: 0,
+            lookups: 0,
+            hits: 0,
+        }
+    }
+
+    // `source` parsed, from the store if it's been seen before; a program bigger than the
+    // whole store is parsed but not kept
+    pub fn get(&mut self, source: &[u8]) -> Result<Program, Error> {
+        self.lookups += 1;
+        let hash = content_hash(source);
+        if let Some(stored) = self.programs.get_mut(&hash) {
+            // a different source with the same hash is a miss, and takes its place
+            if *stored.program.source == *source {
+                stored.last_used = self.lookups;
+                self.hits += 1;
+                return Ok(stored.program.clone());
+            }
+        }
+        let program = Program::parse(source)?;
+        let size = size_of(source);
+        if size <= self.capacity {
+            self.remove(hash);
+            while self.used + size > self.capacity {
+                self.evict();
+            }
+            self.used += size;
+            self.programs.insert(
+                hash,
+                Stored {
+                    program: program.clone(),
+                    size,
+                    last_used: self.lookups,
+                },
+            );
+        }
+        Ok(program)
+    }
+
+    fn remove(&mut self, hash: u64) {
+        if let Some(stored) = self.programs.remove(&hash) {
+            self.used -= stored.size;
+        }
+    }
+
+    // drop the least recently used program
+    fn evict(&mut self) {
+        let oldest = self
+            .programs
+            .iter()
+            .min_by_key(|(_, stored)| stored.last_used)
+            .map(|(&hash, _)| hash);
+        if let Some(hash) = oldest {
+            self.remove(hash);
+        }
+    }
+
+    // how many programs are stored
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+
+    // roughly how many bytes the stored programs take up
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    // how many lookups there have been, and how many found the program already stored
+    pub fn lookups(&self) -> (u64, u64) {
+        (self.lookups, self.hits)
+    }
+}
@@ -0,0 +1,176 @@
+// running a program as a lazy byte source
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::{self, Read, Write},
+    rc::Rc,
+    time::Duration,
+};
+
+use crate::{step::RunStatus, Interpreter};
+
+// output bytes waiting to be read, shared between the machine and the reader
+#[derive(Clone, Default)]
+pub struct Pending(Rc<RefCell<VecDeque<u8>>>);
+
+impl Pending {
+    // everything written so far, leaving nothing pending
+    pub fn take(&self) -> Vec<u8> {
+        self.0.borrow_mut().drain(..).collect()
+    }
+}
+
+impl Write for Pending {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// input handed over a little at a time, for runs that share their time with something else:
+// with nothing fed, reading gives `WouldBlock`, which leaves `run_for` waiting on it, until
+// `close` makes that the end of the input
+#[derive(Clone, Default)]
+pub struct Feed(Rc<RefCell<(VecDeque<u8>, bool)>>);
+
+impl Feed {
+    // add `bytes` to what's left to read
+    pub fn push(&self, bytes: &[u8]) {
+        self.0.borrow_mut().0.extend(bytes);
+    }
+
+    // let reads reach the end once what's been fed is used up
+    pub fn close(&self) {
+        self.0.borrow_mut().1 = true;
+    }
+}
+
+impl Read for Feed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut feed = self.0.borrow_mut();
+        let (queue, closed) = &mut *feed;
+        if queue.is_empty() && !buf.is_empty() {
+            return match closed {
+                true => Ok(0),
+                false => Err(io::ErrorKind::WouldBlock.into()),
+            };
+        }
+        let count = buf.len().min(queue.len());
+        for (slot, byte) in buf.iter_mut().zip(queue.drain(..count)) {
+            *slot = byte;
+        }
+        Ok(count)
+    }
+}
+
+impl Interpreter {
+    // read input from a `Feed` from now on, returning it so bytes can be pushed as they come
+    pub fn feed_input(&mut self) -> Feed {
+        let feed = Feed::default();
+        self.input = Box::new(feed.clone());
+        feed
+    }
+
+    // the program's output a byte at a time, running it only as far as the next byte each
+    // time, so an endless program's output can be taken as it comes. it ends when the program
+    // halts, traps (the trap is left on the machine) or waits on input; the machine writes to
+    // its own output again once the iterator is dropped
+    pub fn output_iter(&mut self) -> OutputIter<'_> {
+        let pending = Pending::default();
+        let output = std::mem::replace(&mut self.output, Box::new(pending.clone()));
+        OutputIter {
+            state: self,
+            pending,
+            output: Some(output),
+        }
+    }
+}
+
+pub struct OutputIter<'a> {
+    state: &'a mut Interpreter,
+    pending: Pending,
+    output: Option<Box<dyn Write>>, // the machine's own, given back on drop
+}
+
+impl Iterator for OutputIter<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            if let Some(byte) = self.pending.0.borrow_mut().pop_front() {
+                return Some(byte);
+            }
+            if self.state.step().is_err() || self.state.status() != RunStatus::Running {
+                // the last step may still have written something
+                return self.pending.0.borrow_mut().pop_front();
+            }
+        }
+    }
+}
+
+impl Drop for OutputIter<'_> {
+    fn drop(&mut self) {
+        if let Some(output) = self.output.take() {
+            self.state.output = output;
+        }
+    }
+}
+
+// a program and its input as an `impl Read` of the program's output: each read runs the
+// interpreter only until it has produced enough bytes, so endless programs can be streamed
+pub struct OutputReader {
+    state: Interpreter,
+    pending: Pending,
+}
+
+impl OutputReader {
+    pub fn new(program: &str, input: &str) -> OutputReader {
+        let mut state = Interpreter::new(program, input);
+        let pending = Pending::default();
+        state.output = Box::new(pending.clone());
+        OutputReader { state, pending }
+    }
+
+    // fail reads once the program has run `steps` steps without halting
+    pub fn step_limit(mut self, steps: usize) -> OutputReader {
+        self.state.max_steps = Some(steps);
+        self
+    }
+
+    // fail reads once the program has run for `limit` without halting
+    pub fn time_limit(mut self, limit: Duration) -> OutputReader {
+        self.state.time_limit = Some(limit);
+        self
+    }
+
+    // whether the program has run to the end, or trapped
+    pub fn halted(&self) -> bool {
+        self.state.idx >= self.state.operations.len()
+    }
+}
+
+impl Read for OutputReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.0.borrow().is_empty() {
+            // a trap comes once what was written before it has been read
+            if let Some(trap) = &self.state.trap {
+                return Err(io::Error::other(trap.message.clone()));
+            }
+            if self.halted() {
+                break;
+            }
+            // a trap, running out of the limits included, stays on the machine for the above
+            let _ = self.state.step();
+        }
+        let mut pending = self.pending.0.borrow_mut();
+        let count = buf.len().min(pending.len());
+        for (slot, byte) in buf.iter_mut().zip(pending.drain(..count)) {
+            *slot = byte;
+        }
+        Ok(count)
+    }
+}
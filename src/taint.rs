@@ -0,0 +1,106 @@
+// `--taint`: which cells hold values derived from input, and so which output bytes depend on
+// it, for working out what an unknown program does with what it's given. a cell read by `,`
+// is tainted, and so is anything written in a loop that goes round on a tainted cell, which
+// is how brainfuck copies and computes; a constant written outside such loops clears it
+use std::{collections::HashSet, ops::Range};
+
+#[derive(Debug, Default)]
+pub struct Taint {
+    cells: HashSet<isize>, // by cell number, counting from the origin
+    loops: Vec<bool>,      // whether each loop running, outermost first, has had a tainted test
+    outputs: Vec<bool>,    // whether each output byte so far depends on input
+}
+
+impl Taint {
+    pub fn new() -> Taint {
+        Taint::default()
+    }
+
+    // whether what runs now only runs because of input
+    fn controlled(&self) -> bool {
+        self.loops.contains(&true)
+    }
+
+    pub fn is_tainted(&self, cell: isize) -> bool {
+        self.cells.contains(&cell)
+    }
+
+    fn set(&mut self, cell: isize, tainted: bool) {
+        match tainted {
+            true => self.cells.insert(cell),
+            false => self.cells.remove(&cell),
+        };
+    }
+
+    // `,` stored a byte in `cell`
+    pub fn input(&mut self, cell: isize) {
+        self.cells.insert(cell);
+    }
+
+    // `cell` was changed by its old value and a constant, as `+` and `-` do
+    pub fn update(&mut self, cell: isize) {
+        let tainted = self.is_tainted(cell) || self.controlled();
+        self.set(cell, tainted);
+    }
+
+    // `cell` was given a new value that doesn't depend on its old one
+    pub fn overwrite(&mut self, cell: isize) {
+        let tainted = self.controlled();
+        self.set(cell, tainted);
+    }
+
+    // a loop testing `cell` was entered
+    pub fn enter(&mut self, cell: isize) {
+        let tainted = self.is_tainted(cell);
+        self.loops.push(tainted);
+    }
+
+    // the innermost loop tested `cell` again and went round
+    pub fn again(&mut self, cell: isize) {
+        let tainted = self.is_tainted(cell);
+        if let Some(test) = self.loops.last_mut() {
+            *test |= tainted;
+        }
+    }
+
+    // the innermost loop finished
+    pub fn leave(&mut self) {
+        self.loops.pop();
+    }
+
+    // output up to byte `len` came from `cell`
+    pub fn output(&mut self, cell: isize, len: usize) {
+        let tainted = self.is_tainted(cell) || self.controlled();
+        self.outputs.resize(len, tainted);
+    }
+
+    pub fn output_len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    // the runs of output bytes that depend on input
+    pub fn tainted_output(&self) -> Vec<Range<usize>> {
+        let mut runs: Vec<Range<usize>> = Vec::new();
+        for (byte, _) in self.outputs.iter().enumerate().filter(|(_, &t)| t) {
+            match runs.last_mut() {
+                Some(run) if run.end == byte => run.end += 1,
+                _ => runs.push(byte..byte + 1),
+            }
+        }
+        runs
+    }
+
+    // the runs of cells holding a value derived from input, in order
+    pub fn tainted_cells(&self) -> Vec<Range<isize>> {
+        let mut cells: Vec<isize> = self.cells.iter().copied().collect();
+        cells.sort_unstable();
+        let mut runs: Vec<Range<isize>> = Vec::new();
+        for cell in cells {
+            match runs.last_mut() {
+                Some(run) if run.end == cell => run.end += 1,
+                _ => runs.push(cell..cell + 1),
+            }
+        }
+        runs
+    }
+}
@@ -0,0 +1,106 @@
+// running programs under the interpreter from rust tests, here or in crates embedding it, e.g.
+//
+//     assert_bf_output!("++++++++[>++++++++<-]>+.", "", "A");
+//     for fixture in testkit::FIXTURES {
+//         assert_bf_output!(fixture, ",[.,]", "echo", "echo");
+//     }
+//
+// every fixture reads 0 at the end of input, the only eof behaviour the interpreter has
+use crate::{error::Error, result::RunResult, Cell, Halt, Interpreter};
+
+// one machine configuration programs can be checked under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixture {
+    pub name: &'static str,
+    pub modulus: Cell, // what cells wrap at; 0 for the full 64 bits
+}
+
+pub const BYTE_CELLS: Fixture = Fixture {
+    name: "8-bit cells",
+    modulus: 256,
+};
+pub const WORD_CELLS: Fixture = Fixture {
+    name: "16-bit cells",
+    modulus: 1 << 16,
+};
+pub const WIDE_CELLS: Fixture = Fixture {
+    name: "32-bit cells",
+    modulus: 1 << 32,
+};
+pub const LONG_CELLS: Fixture = Fixture {
+    name: "64-bit cells",
+    modulus: 0,
+};
+
+// every cell configuration, for checking a program doesn't depend on its cell size
+pub const FIXTURES: &[Fixture] = &[BYTE_CELLS, WORD_CELLS, WIDE_CELLS, LONG_CELLS];
+
+impl Default for Fixture {
+    // what a machine gets when nothing is set, as `Interpreter::new` does
+    fn default() -> Fixture {
+        BYTE_CELLS
+    }
+}
+
+impl Fixture {
+    // a machine for `program` set up the way this fixture says; unmatched brackets are an
+    // error
+    pub fn interpreter(&self, program: &str, input: &str) -> Result<Interpreter, Error> {
+        let mut state = Interpreter::parse(program, input)?;
+        state.memory.modulus = self.modulus;
+        Ok(state)
+    }
+
+    // run `program` on `input` to the end, with its output collected
+    pub fn run(&self, program: &str, input: &str) -> Result<RunResult, Error> {
+        Ok(self.interpreter(program, input)?.run_collect())
+    }
+
+    // whether `program` ends normally on `input` having written exactly `expected`; the error
+    // says what happened instead
+    pub fn check_output(&self, program: &str, input: &str, expected: &[u8]) -> Result<(), String> {
+        let result = self
+            .run(program, input)
+            .map_err(|e| format!("{}: {}", self.name, e))?;
+        if let Some(trap) = &result.trap {
+            let source = program.as_bytes();
+            return Err(format!("{}: trapped: {}", self.name, trap.describe(source)));
+        }
+        if result.halt != Halt::End {
+            return Err(format!("{}: stopped early: {:?}", self.name, result.halt));
+        }
+        if result.output != expected {
+            return Err(format!(
+                "{}: expected {:?}, got {:?}",
+                self.name,
+                String::from_utf8_lossy(expected),
+                result.output_lossy()
+            ));
+        }
+        Ok(())
+    }
+}
+
+// panic unless a program ends normally having written exactly the expected output, which can
+// be anything that's bytes (a `&str`, `&[u8]`, `Vec<u8>`, ...); a fixture can come first to
+// pick the configuration, otherwise it's the default one
+#[macro_export]
+macro_rules! assert_bf_output {
+    ($program:expr, $input:expr, $expected:expr $(,)?) => {
+        $crate::assert_bf_output!(
+            $crate::testkit::Fixture::default(),
+            $program,
+            $input,
+            $expected
+        )
+    };
+    ($fixture:expr, $program:expr, $input:expr, $expected:expr $(,)?) => {
+        if let Err(e) = $fixture.check_output(
+            $program,
+            $input,
+            ::std::convert::AsRef::<[u8]>::as_ref(&$expected),
+        ) {
+            panic!("{}\n  program: {:?}\n  input: {:?}", e, $program, $input);
+        }
+    };
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+// upper bound on distinct marks kept in memory before the time axis gets coarser
+const MAX_MARKS: usize = 200_000;
+const SVG_WIDTH: usize = 1200;
+const SVG_PLOT_HEIGHT: usize = 480;
+const MARGIN: usize = 40;
+
+// the kind of tape activity a mark stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    Pointer,
+    Read,
+    Write,
+    Input,
+    Output,
+}
+
+impl Access {
+    fn colour(self) -> &'static str {
+        match self {
+            Access::Pointer => "#c8c8c8",
+            Access::Read => "#3b7dd8",
+            Access::Write => "#e0842b",
+            Access::Input => "#2ca02c",
+            Access::Output => "#d62728",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Access::Pointer => "pointer",
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Input => "input",
+            Access::Output => "output",
+        }
+    }
+}
+
+// records where the pointer was and what it did over execution time
+pub struct Timeline {
+    marks: BTreeSet<(usize, isize, Access)>, // (time bucket, cell, access)
+    bucket: usize,                           // steps per time bucket
+    steps: usize,
+    // cells count from the origin, so they keep their numbers when the tape grows to the left
+    min_cell: isize,
+    max_cell: isize,
+}
+
+impl Default for Timeline {
+    fn default() -> Timeline {
+        Timeline::new()
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline {
+            marks: BTreeSet::new(),
+            bucket: 1,
+            steps: 0,
+            min_cell: isize::MAX,
+            max_cell: isize::MIN,
+        }
+    }
+
+    // note an access to `cell` at execution step `step`
+    pub fn record(&mut self, step: usize, cell: isize, access: Access) {
+        self.steps = self.steps.max(step + 1);
+        self.min_cell = self.min_cell.min(cell);
+        self.max_cell = self.max_cell.max(cell);
+        self.marks.insert((step / self.bucket, cell, access));
+        if self.marks.len() > MAX_MARKS {
+            self.coarsen();
+        }
+    }
+
+    // halve the time resolution, merging marks that now share a bucket
+    fn coarsen(&mut self) {
+        self.bucket *= 2;
+        self.marks = self
+            .marks
+            .iter()
+            .map(|&(t, cell, access)| (t / 2, cell, access))
+            .collect();
+    }
+
+    // render the recorded activity as a standalone svg document
+    pub fn to_svg(&self) -> String {
+        let columns = self.steps.div_ceil(self.bucket).max(1);
+        let (min_cell, max_cell) = if self.marks.is_empty() {
+            (0, 0)
+        } else {
+            (self.min_cell, self.max_cell)
+        };
+        let rows = (max_cell - min_cell) as usize + 1;
+        let plot_width = SVG_WIDTH - 2 * MARGIN;
+        let row_height = (SVG_PLOT_HEIGHT / rows).clamp(1, 12);
+        let plot_height = row_height * rows;
+        let mark_width = (plot_width / columns).max(1);
+        let height = plot_height + 3 * MARGIN;
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{SVG_WIDTH}\" height=\"{height}\" font-family=\"monospace\" font-size=\"11\">"
+        );
+        let _ = writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>");
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{MARGIN}\" y=\"{MARGIN}\" width=\"{plot_width}\" height=\"{plot_height}\" fill=\"none\" stroke=\"#888\"/>"
+        );
+
+        // marks that land on the same pixel are only drawn once
+        let mut drawn = BTreeSet::new();
+        for &(t, cell, access) in &self.marks {
+            let x = MARGIN + t * plot_width / columns;
+            let y = MARGIN + (cell - min_cell) as usize * row_height;
+            if drawn.insert((access, x, y)) {
+                let _ = writeln!(
+                    svg,
+                    "<rect x=\"{x}\" y=\"{y}\" width=\"{mark_width}\" height=\"{row_height}\" fill=\"{}\"/>",
+                    access.colour()
+                );
+            }
+        }
+
+        // axis labels and legend
+        let bottom = MARGIN + plot_height;
+        let _ = writeln!(
+            svg,
+            "<text x=\"{MARGIN}\" y=\"{}\">step 0</text>",
+            bottom + 14
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">step {}</text>",
+            MARGIN + plot_width,
+            bottom + 14,
+            self.steps
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{MARGIN}\" y=\"{}\">cell {}</text>",
+            MARGIN - 6,
+            min_cell
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">cell {} (last row)</text>",
+            MARGIN + plot_width,
+            MARGIN - 6,
+            max_cell
+        );
+        let accesses = [
+            Access::Pointer,
+            Access::Read,
+            Access::Write,
+            Access::Input,
+            Access::Output,
+        ];
+        for (i, access) in accesses.iter().enumerate() {
+            let x = MARGIN + i * 100;
+            let y = bottom + 30;
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{x}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{}\"/><text x=\"{}\" y=\"{y}\">{}</text>",
+                y - 9,
+                access.colour(),
+                x + 14,
+                access.label()
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
@@ -0,0 +1,55 @@
+// `--trace-loops`: a log of loops starting and finishing, with how many times each went round
+use std::io::{self, Write};
+
+pub struct LoopTrace {
+    open: Vec<u64>, // iterations so far of each loop currently running, outermost first
+    out: Box<dyn Write>,
+}
+
+impl Default for LoopTrace {
+    fn default() -> LoopTrace {
+        LoopTrace::new()
+    }
+}
+
+impl LoopTrace {
+    pub fn new() -> LoopTrace {
+        LoopTrace {
+            open: Vec::new(),
+            out: Box::new(io::stderr()),
+        }
+    }
+
+    fn line(&mut self, text: String) {
+        let indent = "  ".repeat(self.open.len());
+        let _ = writeln!(self.out, "{}{}", indent, text);
+    }
+
+    // a `[` was reached; `taken` says whether the loop body runs
+    pub fn start(&mut self, place: String, taken: bool) {
+        match taken {
+            true => {
+                self.line(format!("loop at {}: entered", place));
+                self.open.push(1);
+            }
+            false => self.line(format!("loop at {}: skipped", place)),
+        }
+    }
+
+    // a `]` sent control back for another iteration
+    pub fn again(&mut self) {
+        if let Some(count) = self.open.last_mut() {
+            *count += 1;
+        }
+    }
+
+    // a `]` let control fall through; `place` is the loop's `[`
+    pub fn finish(&mut self, place: String) {
+        let count = self.open.pop().unwrap_or(0);
+        let plural = if count == 1 { "" } else { "s" };
+        self.line(format!(
+            "loop at {}: exited after {} iteration{}",
+            place, count, plural
+        ));
+    }
+}
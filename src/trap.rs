@@ -0,0 +1,44 @@
+// errors that stop a running program, with where in the source they happened
+use std::fmt::Write;
+
+use crate::program::{line_col, Span};
+
+#[derive(Debug, Clone)]
+pub struct Trap {
+    pub message: String,
+    pub index: usize,        // the instruction that trapped
+    pub span: Option<Span>,  // its source, when the program came with spans
+    pub loops: Vec<LoopRef>, // the loops it was inside, innermost first
+}
+
+// an enclosing loop, by instruction index and source span of each bracket
+#[derive(Debug, Clone, Copy)]
+pub struct LoopRef {
+    pub start: usize,
+    pub end: usize,
+    pub spans: Option<(Span, Span)>,
+}
+
+impl Trap {
+    // the error as text, with lines and columns found in `source`
+    pub fn describe(&self, source: &[u8]) -> String {
+        let at = |index: usize, span: Option<Span>| match span {
+            Some(span) => {
+                let (line, column) = line_col(source, span.start);
+                format!("line {}, column {}", line, column)
+            }
+            None => format!("instruction {}", index),
+        };
+        let mut text = format!("{}\n  at {}", self.message, at(self.index, self.span));
+        for found in &self.loops {
+            let (start, end) = found.spans.unzip();
+            let _ = write!(
+                text,
+                "\n  in the loop from {} to {}",
+                at(found.start, start),
+                at(found.end, end)
+            );
+        }
+        text
+    }
+}
@@ -0,0 +1,26 @@
+// what a run has used so far, for quotas and per-run metrics
+use std::mem;
+
+use crate::{Cell, Interpreter};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceUsage {
+    pub tape_bytes: usize,    // memory held by the tape
+    pub cells_touched: usize, // from the lowest cell the pointer reached to the highest
+    pub input_bytes: usize,   // bytes read by `,`
+    pub output_bytes: usize,  // bytes written
+    pub steps: usize,
+}
+
+impl Interpreter {
+    pub fn usage(&self) -> ResourceUsage {
+        let memory = &self.memory;
+        ResourceUsage {
+            tape_bytes: memory.bytearray.len() * mem::size_of::<Cell>(),
+            cells_touched: memory.reached().len(),
+            input_bytes: self.input_len,
+            output_bytes: self.output_len,
+            steps: self.steps,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// reading numbers a program left on the tape, so it needn't print them itself
+use std::ops::Range;
+
+use crate::Memory;
+
+// how a range of cells spells its values
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ValueFormat {
+    #[default]
+    U8, // each cell is one number
+    U16Le, // every two cells are a little-endian number, a byte per cell
+    U32Le,
+    U64Le,
+    Dec, // the cells are decimal digits, most significant first, making one number
+}
+
+impl ValueFormat {
+    pub fn parse(name: &str) -> Option<ValueFormat> {
+        match name {
+            "u8" => Some(ValueFormat::U8),
+            "u16le" => Some(ValueFormat::U16Le),
+            "u32le" => Some(ValueFormat::U32Le),
+            "u64le" => Some(ValueFormat::U64Le),
+            "dec" => Some(ValueFormat::Dec),
+            _ => None,
+        }
+    }
+}
+
+// the values in cells `range` (counting from the origin)
+pub fn read_values(
+    memory: &Memory,
+    range: Range<isize>,
+    format: ValueFormat,
+) -> Result<Vec<u64>, String> {
+    let (start, cells) = memory.window(range.clone());
+    if start != range.start || cells.len() != range.len() {
+        return Err(format!(
+            "cells {}..{} aren't all on the tape",
+            range.start, range.end
+        ));
+    }
+    let width = match format {
+        ValueFormat::U8 => return Ok(cells.to_vec()),
+        ValueFormat::Dec => {
+            let mut value: u64 = 0;
+            for &digit in cells {
+                if digit > 9 {
+                    return Err(format!("{} isn't a decimal digit", digit));
+                }
+                value = value
+                    .checked_mul(10)
+                    .and_then(|value| value.checked_add(digit))
+                    .ok_or("the number doesn't fit in 64 bits")?;
+            }
+            return Ok(vec![value]);
+        }
+        ValueFormat::U16Le => 2,
+        ValueFormat::U32Le => 4,
+        ValueFormat::U64Le => 8,
+    };
+    if cells.len() % width != 0 {
+        return Err(format!(
+            "{} cells don't divide into {}-byte numbers",
+            cells.len(),
+            width
+        ));
+    }
+    Ok(cells
+        .chunks(width)
+        .map(|bytes| {
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, &byte| value << 8 | (byte & 0xff))
+        })
+        .collect())
+}
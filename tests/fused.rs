@@ -0,0 +1,114 @@
+// the fused instructions plain runs use have to end up exactly where running the program one
+// command at a time does, under every cell size the testkit has
+use brainfuck_jit::{
+    assert_bf_output,
+    ir::{compile, Ir},
+    observer::Observer,
+    testkit::{Fixture, FIXTURES},
+    Cell, Interpreter,
+};
+
+// an observer that does nothing, just to make a run go a step at a time
+struct Stepped;
+
+impl Observer for Stepped {}
+
+// where a run ended up, to compare one way of running with the other
+#[derive(Debug, PartialEq)]
+struct End {
+    output: Vec<u8>,
+    tape: Vec<Cell>,
+    pointer: isize,
+    steps: usize,
+    trap: Option<String>,
+}
+
+fn run(fixture: Fixture, program: &str, input: &str, fused: bool, limit: Option<usize>) -> End {
+    let mut state = fixture.interpreter(program, input).unwrap();
+    state.max_steps = limit;
+    if !fused {
+        state.observe(Stepped);
+    }
+    assert_eq!(state.watched(), !fused);
+    let result = state.run_collect();
+    End {
+        output: result.output,
+        tape: state.tape().to_vec(),
+        pointer: state.pointer(),
+        steps: state.steps,
+        trap: result.trap.map(|trap| trap.message),
+    }
+}
+
+// check `program` compiles to something `fused` picks out, and that running that gives the
+// same as stepping, to the end and stopped partway through by a step limit
+fn check(program: &str, input: &str, fused: impl Fn(&Ir) -> bool) {
+    let state = Interpreter::parse(program, input).unwrap();
+    let code = compile(&state.operations);
+    assert!(
+        code.iter().any(|instruction| fused(&instruction.ir)),
+        "{:?} doesn't compile to the instruction being tested",
+        program
+    );
+    for &fixture in FIXTURES {
+        let whole = run(fixture, program, input, false, None);
+        assert_eq!(
+            run(fixture, program, input, true, None),
+            whole,
+            "{}: {:?}",
+            fixture.name,
+            program
+        );
+        for limit in 0..=whole.steps {
+            assert_eq!(
+                run(fixture, program, input, true, Some(limit)),
+                run(fixture, program, input, false, Some(limit)),
+                "{}: {:?} limited to {} steps",
+                fixture.name,
+                program,
+                limit
+            );
+        }
+    }
+}
+
+#[test]
+fn set_zero() {
+    check("+++++[-]>--[+]>+++.", "", |ir| matches!(ir, Ir::SetZero(_)));
+    check(",[-].", "a", |ir| matches!(ir, Ir::SetZero(_)));
+}
+
+#[test]
+fn mul() {
+    check("+++++[->++>+++<<]>.>.", "", |ir| matches!(ir, Ir::Mul(_)));
+    check("++++[->---<]>.", "", |ir| matches!(ir, Ir::Mul(_)));
+    check("++[>+++[->++<]<-]>>.", "", |ir| matches!(ir, Ir::Mul(_)));
+    check(">>+++[-<<+>+>]<<.", "", |ir| matches!(ir, Ir::Mul(_)));
+}
+
+#[test]
+fn scan() {
+    check("+>+>+<<[>]+.", "", |ir| matches!(ir, Ir::Scan(1)));
+    check(">>>>+<+<+[<]+.", "", |ir| matches!(ir, Ir::Scan(-1)));
+    check("+>>+>>+<<<<[>>]+.", "", |ir| matches!(ir, Ir::Scan(2)));
+}
+
+#[test]
+fn add_and_move() {
+    check("+++>>><<-->+-+++++<>", "", |ir| matches!(ir, Ir::Add(3)));
+    check("+++>>><<-->+-+++++<>", "", |ir| matches!(ir, Ir::Move(3)));
+    check("+-+-><.", "", |ir| matches!(ir, Ir::Add(0) | Ir::Move(0)));
+    check(",[>++<-.,]>.", "abc", |ir| matches!(ir, Ir::Add(2)));
+    // off the left end round to the far one, then past that as the tape grows
+    check("+<<<+++>>>>.<.", "", |ir| matches!(ir, Ir::Move(-3)));
+}
+
+#[test]
+fn fused_output() {
+    assert_bf_output!("++++++++[>++++++++<-]>+.", "", "A");
+    assert_bf_output!("+++++[-]>--[+]>+++.", "", [3]);
+    for &fixture in FIXTURES {
+        assert_bf_output!(fixture, ",[.,]", "echo", "echo");
+        assert_bf_output!(fixture, "+>+>+<<[>]+.", "", [1]);
+    }
+}